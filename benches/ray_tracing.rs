@@ -15,8 +15,7 @@ fn bench_targets(c: &mut Criterion) {
             let mut renderer = RayTracing::new(512, 512, 42);
             renderer.move_camera_to(Vec3::new(0., 0., 3.));
             renderer.rotate_camera_to(Vec3::new(1., 0., -0.5));
-            renderer.put_light(Light::new(Vec3::new(0., 5., 2.), 1.));
-            renderer.put_light(Light::new(Vec3::new(0., -5., 2.), -1.));
+            renderer.put_light(Light::new(Vec3::new(0., 5., 2.), 30.));
             for i in 0..3 {
                 renderer.put_sphere(Sphere::new(Vec3::new(i as f32 * 3., 0., 2.), 1.));
             }
@@ -29,6 +28,34 @@ fn bench_targets(c: &mut Criterion) {
             start_time.elapsed()
         })
     });
+
+    for count in [1_000, 10_000] {
+        c.bench_function(&format!("RayTracing::render {count} spheres"), |b| {
+            b.iter_custom(|iters| {
+                let mut renderer = RayTracing::new(256, 256, 42);
+                renderer.move_camera_to(Vec3::new(-5., 0., 5.));
+                renderer.rotate_camera_to(Vec3::new(1., 0., -0.5));
+                renderer.put_light(Light::new(Vec3::new(0., 5., 8.), 30.));
+                renderer.put_light(Light::new(Vec3::new(0., -5., 8.), 30.));
+                // 铺成一个正方形的球阵.
+                let side = (count as f32).sqrt().ceil() as usize;
+                for i in 0..count {
+                    let (x, y) = ((i % side) as f32, (i / side) as f32);
+                    renderer.put_sphere(Sphere::new(
+                        Vec3::new(x * 0.5, y * 0.5 - side as f32 * 0.25, 0.2),
+                        0.2,
+                    ));
+                }
+
+                let start_time = Instant::now();
+                for _ in 0..iters {
+                    renderer.trigger_action(Action::RequestRender);
+                    renderer.render();
+                }
+                start_time.elapsed()
+            })
+        });
+    }
 }
 
 criterion_group!(
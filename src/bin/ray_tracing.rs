@@ -1,8 +1,11 @@
 use std::time::Duration;
 
-use minifb::{Key, Window, WindowOptions};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
 use render3d::fps::FpsCounter;
-use render3d::ray_tracing::{Light, RayTracing, Sphere, action::Action, vector::Vec3};
+use render3d::ray_tracing::{
+    Handle, RayTracing, Sphere, action::Action, camera::Projection, settings::Integrator,
+    sky::PhysicalSky, tone_mapping::ToneMapping, vector::Vec3,
+};
 
 fn key_map(key: Key) -> Option<Action> {
     match key {
@@ -20,6 +23,253 @@ fn key_map(key: Key) -> Option<Action> {
     }
 }
 
+/// `-`/`=` 调整抗锯齿采样次数, `[`/`]` 调整最大反射次数, `P` 切换路径追踪.
+fn adjust_settings(window: &Window, renderer: &mut RayTracing) {
+    let mut settings = renderer.settings();
+    let pressed = |key| window.is_key_pressed(key, KeyRepeat::No);
+    if pressed(Key::Equal) {
+        settings.aa_samples = settings.aa_samples.saturating_add(1);
+    } else if pressed(Key::Minus) {
+        settings.aa_samples = settings.aa_samples.saturating_sub(1).max(1);
+    } else if pressed(Key::RightBracket) {
+        settings.max_reflection = settings.max_reflection.saturating_add(1);
+    } else if pressed(Key::LeftBracket) {
+        settings.max_reflection = settings.max_reflection.saturating_sub(1);
+    } else if pressed(Key::Period) {
+        settings.aperture += 0.02;
+    } else if pressed(Key::Comma) {
+        settings.aperture = (settings.aperture - 0.02).max(0.);
+    } else if pressed(Key::F) {
+        settings.autofocus = !settings.autofocus;
+    } else if pressed(Key::Key0) {
+        settings.exposure += 0.5;
+    } else if pressed(Key::Key9) {
+        settings.exposure -= 0.5;
+    } else if pressed(Key::M) {
+        settings.tone_mapping = match settings.tone_mapping {
+            ToneMapping::Clamp => ToneMapping::Reinhard,
+            ToneMapping::Reinhard => ToneMapping::Aces,
+            ToneMapping::Aces => ToneMapping::Clamp,
+        };
+    } else if pressed(Key::P) {
+        settings.integrator = match settings.integrator {
+            Integrator::Whitted => Integrator::PathTracing,
+            Integrator::PathTracing => Integrator::Whitted,
+        };
+    } else {
+        return;
+    }
+    renderer.set_settings(settings);
+}
+
+/// 按 O 切换相机的投影方式.
+fn switch_projection(window: &Window, renderer: &mut RayTracing) {
+    if !window.is_key_pressed(Key::O, KeyRepeat::No) {
+        return;
+    }
+    let mut camera = renderer.camera();
+    camera.set_projection(match camera.projection() {
+        Projection::Perspective => Projection::Orthographic,
+        Projection::Orthographic => Projection::Fisheye,
+        Projection::Fisheye => Projection::Equirectangular,
+        Projection::Equirectangular => Projection::Perspective,
+    });
+    renderer.set_camera(camera);
+}
+
+/// 按 T 开关物理天空的昼夜循环, 开启时每帧推进一点时间.
+fn day_cycle(window: &Window, renderer: &mut RayTracing, hours: &mut Option<f32>) {
+    /// 每帧推进的时间 (小时).
+    const HOURS_PER_FRAME: f32 = 0.05;
+    if window.is_key_pressed(Key::T, KeyRepeat::No) {
+        *hours = match hours {
+            Some(_) => {
+                renderer.set_physical_sky(None);
+                None
+            }
+            None => {
+                renderer.set_physical_sky(Some(PhysicalSky::new(0., 0., 3.)));
+                Some(5.)
+            }
+        };
+    }
+    if let Some(hours) = hours {
+        *hours = (*hours + HOURS_PER_FRAME).rem_euclid(24.);
+        renderer.set_time_of_day(*hours);
+    }
+}
+
+/// 按 F2 保存 PNG 截图, 按 F3 保存线性颜色的 EXR, 按 F4 保存场景文件.
+fn save_files(window: &Window, renderer: &RayTracing) {
+    let pressed = |key| window.is_key_pressed(key, KeyRepeat::No);
+    let (path, result) = if pressed(Key::F2) {
+        ("screenshot.png", renderer.save_image("screenshot.png"))
+    } else if pressed(Key::F3) {
+        ("screenshot.exr", renderer.save_image("screenshot.exr"))
+    } else if pressed(Key::F4) {
+        let result = renderer
+            .to_scene_string()
+            .and_then(|src| std::fs::write("scene.json", src).map_err(|e| e.to_string()));
+        ("scene.json", result)
+    } else {
+        return;
+    };
+    match result {
+        Ok(()) => println!("saved {path}"),
+        Err(e) => eprintln!("failed to save {path}: {e}"),
+    }
+}
+
+/// 用鼠标编辑场景:
+///
+/// - 左键点击选中物体并打印它的信息, 选中的物体会描出轮廓, 点击天空取消选中;
+/// - 按住左键拖动选中的球体在水平面上移动, 同时按住 Shift 则沿视线方向前后移动;
+/// - 滚轮缩放选中的球体, 没有选中球体时缩放相机视野;
+/// - Delete 删除选中的物体.
+#[derive(Default)]
+struct Editor {
+    selected: Option<Handle>,
+    /// 上一帧左键是否按下.
+    was_down: bool,
+    drag: Option<Drag>,
+}
+
+/// 正在进行的拖动.
+#[derive(Clone, Copy)]
+struct Drag {
+    /// 抓住的点, 跟着球体一起移动.
+    point: Vec3,
+    /// 上一帧鼠标的像素坐标.
+    mouse: (f32, f32),
+}
+
+impl Editor {
+    /// 滚轮每滚一格球体半径缩放的比例.
+    const SCALE_PER_SCROLL: f32 = 1.1;
+
+    fn update(&mut self, window: &Window, renderer: &mut RayTracing) {
+        if window.is_key_pressed(Key::Delete, KeyRepeat::No)
+            && let Some(handle) = self.selected.take()
+        {
+            renderer.remove(handle);
+            renderer.set_highlight(None);
+            self.drag = None;
+            println!("removed {handle}");
+        }
+
+        let down = window.get_mouse_down(MouseButton::Left);
+        let mouse = window.get_mouse_pos(MouseMode::Clamp);
+        match (down, self.was_down, mouse) {
+            (true, false, Some(mouse)) => self.select(renderer, mouse),
+            (true, true, Some(mouse)) => self.drag_to(window, renderer, mouse),
+            _ => self.drag = None,
+        }
+        self.was_down = down;
+
+        if let Some((_, scroll)) = window.get_scroll_wheel()
+            && scroll != 0.
+        {
+            let resized = self.update_sphere(renderer, |sphere| {
+                sphere.set_radius(sphere.radius() * Self::SCALE_PER_SCROLL.powf(scroll.signum()));
+            });
+            if !resized {
+                renderer.zoom(scroll.signum());
+            }
+        }
+    }
+
+    /// 选中鼠标点到的物体, 点到球体时开始拖动.
+    fn select(&mut self, renderer: &mut RayTracing, mouse: (f32, f32)) {
+        let pick = renderer.pick(mouse.0 as usize, mouse.1 as usize);
+        self.selected = pick.and_then(|pick| pick.handle());
+        renderer.set_highlight(self.selected);
+        self.drag = None;
+        let Some(pick) = pick else {
+            println!("Sky");
+            return;
+        };
+        println!(
+            "{:?} {:?} at {:?}, normal {:?}, distance {:.3}",
+            pick.kind(),
+            pick.handle(),
+            pick.hit_point(),
+            pick.normal(),
+            pick.distance()
+        );
+        if let Some(handle) = self.selected
+            && renderer.sphere(handle).is_some()
+        {
+            self.drag = Some(Drag {
+                point: pick.hit_point(),
+                mouse,
+            });
+        }
+    }
+
+    /// 按照鼠标的移动拖动选中的球体.
+    fn drag_to(&mut self, window: &Window, renderer: &mut RayTracing, mouse: (f32, f32)) {
+        let Some(drag) = self.drag else {
+            return;
+        };
+        let offset = if window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift) {
+            // 鼠标向上移动时远离相机, 移动的距离和物体到相机的距离成正比.
+            let camera = renderer.camera();
+            let distance = (drag.point - camera.pos()).magnitude();
+            let height = window.get_size().1 as f32;
+            camera.gaze() * ((drag.mouse.1 - mouse.1) / height * distance)
+        } else {
+            // 鼠标光线和抓住的点所在水平面的交点就是新的位置.
+            let Some((origin, direction)) = renderer.camera_ray(mouse.0, mouse.1) else {
+                return;
+            };
+            let t = (drag.point.z - origin.z) / direction.z;
+            if direction.z.abs() < 1e-4 || t <= 0. {
+                return;
+            }
+            origin + direction * t - drag.point
+        };
+        self.drag = Some(Drag {
+            point: drag.point + offset,
+            mouse,
+        });
+        // 鼠标没动时不修改场景, 否则渐进式渲染会一直重新开始.
+        if !offset.is_zero() {
+            self.update_sphere(renderer, |sphere| {
+                sphere.set_center(sphere.center() + offset);
+            });
+        }
+    }
+
+    /// 修改选中的球体, 选中的不是球体时什么也不做并返回 false.
+    fn update_sphere(&self, renderer: &mut RayTracing, f: impl FnOnce(&mut Sphere)) -> bool {
+        let Some(handle) = self.selected else {
+            return false;
+        };
+        let Some(mut sphere) = renderer.sphere(handle) else {
+            return false;
+        };
+        f(&mut sphere);
+        renderer.update_sphere(handle, sphere).unwrap();
+        true
+    }
+}
+
+/// 按住右键移动鼠标转动视角, `last` 记录上一帧右键按下时鼠标的位置.
+fn mouse_look(window: &Window, renderer: &mut RayTracing, last: &mut Option<(f32, f32)>) {
+    let mouse = window
+        .get_mouse_pos(MouseMode::Pass)
+        .filter(|_| window.get_mouse_down(MouseButton::Right));
+    if let (Some((x, y)), Some((last_x, last_y))) = (mouse, *last)
+        && (x, y) != (last_x, last_y)
+    {
+        renderer.look(x - last_x, y - last_y);
+    }
+    *last = mouse;
+}
+
+/// 和网页共用的默认场景.
+const DEFAULT_SCENE: &str = include_str!("../../www/scenes/spheres.json");
+
 fn main() {
     const HEIGHT: usize = 300;
     const WIDTH: usize = 300;
@@ -40,22 +290,41 @@ fn main() {
     window.set_target_fps(30); // 60 帧会消耗很多的 cpu, 需要使用 release profile 才有较好的帧率.
 
     let mut fps_counter = FpsCounter::new(Duration::from_secs(1));
-    let mut renderer = RayTracing::new(HEIGHT, WIDTH, 42);
-    renderer.move_camera_to(Vec3::new(0., 0., 4.));
-    renderer.rotate_camera_to(Vec3::new(1., 0., -3.));
-    for x in (0..10u8).step_by(3) {
-        renderer.put_sphere(Sphere::new(Vec3::new(x.into(), 0., 1.), 1.));
-    }
-    renderer.put_light(Light::new(Vec3::new(5., 5., 3.), 1.));
-    renderer.put_light(Light::new(Vec3::new(5., -5., 3.), 1.0));
+    // 可以在命令行传入其他场景文件 (.json) 或者 glTF 场景 (.gltf, .glb).
+    let mut renderer = match std::env::args().nth(1) {
+        Some(path) if path.ends_with(".gltf") || path.ends_with(".glb") => {
+            let mut renderer = RayTracing::new(WIDTH, HEIGHT, 42);
+            renderer.set_ground(None);
+            renderer.import_gltf_file(&path).unwrap();
+            renderer
+        }
+        Some(path) => {
+            let src = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{path}: {e}"));
+            RayTracing::from_scene_str(&src, WIDTH, HEIGHT, 42).unwrap()
+        }
+        None => RayTracing::from_scene_str(DEFAULT_SCENE, WIDTH, HEIGHT, 42).unwrap(),
+    };
     let mut buffer = renderer.render().unwrap();
+    let mut hours = None;
+    let mut editor = Editor::default();
+    let mut last_mouse = None;
     while window.is_open() && !window.is_key_down(minifb::Key::Escape) {
-        window.set_title(&format!("{title}, fps: {:.2}", fps_counter.tick()));
+        window.set_title(&format!(
+            "{title}, fps: {:.2}, passes: {}",
+            fps_counter.tick(),
+            renderer.accumulated_passes()
+        ));
         window
             .get_keys()
             .into_iter()
             .filter_map(key_map)
             .for_each(|a| renderer.trigger_action(a));
+        adjust_settings(&window, &mut renderer);
+        switch_projection(&window, &mut renderer);
+        day_cycle(&window, &mut renderer, &mut hours);
+        save_files(&window, &renderer);
+        editor.update(&window, &mut renderer);
+        mouse_look(&window, &mut renderer, &mut last_mouse);
         if let Some(b) = renderer.render() {
             buffer = b;
         }
@@ -0,0 +1,195 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use clap::{Parser, ValueEnum};
+use render3d::ray_tracing::{
+    Light, RayTracing, camera::Camera, gltf_import::GltfScene, material::Material, mesh::Mesh,
+    settings::Integrator, tone_mapping::ToneMapping, vector::Vec3,
+};
+
+/// 不打开窗口, 渲染一帧并保存成图片.
+#[derive(Parser)]
+struct MArgs {
+    #[clap(help = "Scene file (.json, .gltf, .glb) or model (.obj)")]
+    scene: PathBuf,
+    #[clap(
+        short,
+        long,
+        help = "Output image (.png, .ppm or .exr)",
+        default_value = "render.png"
+    )]
+    output: PathBuf,
+    #[clap(long, help = "Image width in pixels", default_value_t = 800)]
+    width: usize,
+    #[clap(long, help = "Image height in pixels", default_value_t = 600)]
+    height: usize,
+    #[clap(long, help = "Samples per pixel", default_value_t = 64)]
+    spp: u32,
+    #[clap(long, help = "Random seed", default_value_t = 42)]
+    seed: u32,
+    #[clap(
+        long,
+        help = "Integrator, defaults to the scene's setting for .json and path otherwise",
+        value_enum
+    )]
+    integrator: Option<IntegratorArg>,
+    #[clap(long, help = "Camera position, e.g. 0,-6,3", value_parser = parse_vec3)]
+    camera_pos: Option<Vec3>,
+    #[clap(long, help = "Point the camera looks at, e.g. 0,0,1", value_parser = parse_vec3)]
+    look_at: Option<Vec3>,
+    #[clap(long, help = "Vertical field of view in degrees")]
+    fov: Option<f32>,
+    #[clap(long, help = "Lens aperture radius in metres")]
+    aperture: Option<f32>,
+    #[clap(long, help = "Focus distance in metres, disables autofocus")]
+    focus_distance: Option<f32>,
+    #[clap(long, help = "Exposure compensation in EV")]
+    exposure: Option<f32>,
+    #[clap(long, help = "Tone mapping operator", value_enum)]
+    tone_mapping: Option<ToneMappingArg>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum IntegratorArg {
+    Whitted,
+    Path,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ToneMappingArg {
+    Clamp,
+    Reinhard,
+    Aces,
+}
+
+/// 解析 `x,y,z` 形式的向量.
+fn parse_vec3(s: &str) -> Result<Vec3, String> {
+    let values = s
+        .split(',')
+        .map(|v| v.trim().parse::<f32>().map_err(|e| format!("{v:?}: {e}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    match values[..] {
+        [x, y, z] => Ok(Vec3::new(x, y, z)),
+        _ => Err(format!("expected x,y,z, got {s:?}")),
+    }
+}
+
+/// 加载场景文件.
+///
+/// OBJ 模型会被放在默认场景 (棋盘格地面, 两盏灯) 的原点上,
+/// glTF 场景不使用默认的地面, 没有光源或者相机时使用默认的光源和相机.
+fn load_scene(args: &MArgs) -> Result<RayTracing, String> {
+    let path = &args.scene;
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let mut renderer = RayTracing::new(args.width, args.height, args.seed);
+    let (has_lights, has_camera) = match extension.as_str() {
+        "json" => {
+            let src =
+                std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+            return RayTracing::from_scene_str(&src, args.width, args.height, args.seed);
+        }
+        "gltf" | "glb" => {
+            let scene = GltfScene::open(path)?;
+            // glTF 场景有自己的地面.
+            renderer.set_ground(None);
+            renderer.put_gltf(&scene)?;
+            (!scene.lights.is_empty(), scene.camera.is_some())
+        }
+        _ => {
+            let bytes = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+            let mut mesh = Mesh::from_obj(&bytes, Material::diffuse(Vec3::new(0.8, 0.8, 0.8)))?;
+            mesh.y_up_to_z_up();
+            mesh.fit_to(Vec3::ZERO, 2.);
+            renderer.put_mesh(mesh);
+            (false, false)
+        }
+    };
+    if !has_lights {
+        renderer.put_light(Light::sphere(Vec3::new(4., -4., 5.), 0.5, 125.));
+        renderer.put_light(Light::new(Vec3::new(-4., -3., 4.), 50.));
+    }
+    if !has_camera {
+        renderer.set_camera(Camera::look_at(
+            Vec3::new(0., -5., 2.5),
+            Vec3::new(0., 0., 1.),
+            Vec3::Z,
+        ));
+    }
+    let mut settings = renderer.settings();
+    settings.integrator = Integrator::PathTracing;
+    renderer.set_settings(settings);
+    Ok(renderer)
+}
+
+fn apply_overrides(renderer: &mut RayTracing, args: &MArgs) {
+    let mut camera = renderer.camera();
+    if let Some(pos) = args.camera_pos {
+        camera.set_pos(pos);
+    }
+    if let Some(target) = args.look_at {
+        camera.set_gaze(target - camera.pos());
+    }
+    if let Some(fov) = args.fov {
+        camera.set_fov(fov);
+    }
+    renderer.set_camera(camera);
+
+    let mut settings = renderer.settings();
+    // 每一遍每个像素只采样一次, 这样可以按遍数打印进度.
+    settings.aa_samples = 1;
+    settings.progressive = true;
+    settings.max_progressive_passes = args.spp.max(1);
+    if let Some(integrator) = args.integrator {
+        settings.integrator = match integrator {
+            IntegratorArg::Whitted => Integrator::Whitted,
+            IntegratorArg::Path => Integrator::PathTracing,
+        };
+    }
+    if let Some(aperture) = args.aperture {
+        settings.aperture = aperture;
+    }
+    if let Some(focus_distance) = args.focus_distance {
+        settings.focus_distance = focus_distance;
+        settings.autofocus = false;
+    }
+    if let Some(exposure) = args.exposure {
+        settings.exposure = exposure;
+    }
+    if let Some(tone_mapping) = args.tone_mapping {
+        settings.tone_mapping = match tone_mapping {
+            ToneMappingArg::Clamp => ToneMapping::Clamp,
+            ToneMappingArg::Reinhard => ToneMapping::Reinhard,
+            ToneMappingArg::Aces => ToneMapping::Aces,
+        };
+    }
+    renderer.set_settings(settings);
+}
+
+fn main() -> Result<(), String> {
+    let args = MArgs::parse();
+    let mut renderer = load_scene(&args)?;
+    apply_overrides(&mut renderer, &args);
+
+    let start = Instant::now();
+    let passes = args.spp.max(1);
+    for pass in 1..=passes {
+        renderer.render();
+        eprint!(
+            "\rrendering {}x{}: {pass}/{passes} samples, {:.1}s",
+            args.width,
+            args.height,
+            start.elapsed().as_secs_f32()
+        );
+        std::io::stderr().flush().ok();
+    }
+    eprintln!();
+
+    renderer.save_image(&args.output)?;
+    eprintln!("saved {}", args.output.display());
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use wasm_bindgen::prelude::wasm_bindgen;
 
 #[wasm_bindgen]
@@ -20,15 +20,44 @@ pub enum Action {
     RequestRender,
 }
 
+/// 模拟量输入 (比如手柄摇杆), 取值范围 -1.0 ~ 1.0, 绝对值表示速度占最大速度的比例.
+///
+/// 和 [`Action`] 一样, 默认在 render 之后和 action 一起被清空,
+/// 调用 [`set_withdraw_actions_on_render(false)`](super::RayTracing::set_withdraw_actions_on_render)
+/// 之后才会一直生效, 直到被设置成 0.
+#[wasm_bindgen]
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub enum Axis {
+    /// 向前移动, 负数向后.
+    CameraMoveForward,
+    /// 向右移动, 负数向左.
+    CameraMoveRight,
+    /// 向上移动, 负数向下.
+    CameraMoveUp,
+    /// 向右转, 负数向左转.
+    CameraTurnRight,
+    /// 向上看, 负数向下看.
+    CameraTurnUp,
+}
+
 #[derive(Debug)]
 pub(crate) struct ActionManager {
     actions: HashSet<Action>,
+    /// 不为 0 的模拟量输入.
+    axes: HashMap<Axis, f32>,
+    /// 还没有处理的鼠标移动量 (像素), 向右和向下为正.
+    look: (f32, f32),
+    /// 还没有处理的缩放量 (滚轮格数), 正数放大.
+    zoom: f32,
 }
 
 impl ActionManager {
     pub(crate) fn new() -> Self {
         Self {
             actions: HashSet::new(),
+            axes: HashMap::new(),
+            look: (0., 0.),
+            zoom: 0.,
         }
     }
 
@@ -44,11 +73,51 @@ impl ActionManager {
         self.actions.contains(&action)
     }
 
+    /// 设置模拟量, 超出范围的值会被截断到 -1.0 ~ 1.0.
+    pub(crate) fn set_axis(&mut self, axis: Axis, value: f32) {
+        let value = value.clamp(-1., 1.);
+        if value == 0. || value.is_nan() {
+            self.axes.remove(&axis);
+        } else {
+            self.axes.insert(axis, value);
+        }
+    }
+
+    pub(crate) fn axis(&self, axis: Axis) -> f32 {
+        self.axes.get(&axis).copied().unwrap_or_default()
+    }
+
+    /// 累加鼠标移动量.
+    pub(crate) fn look(&mut self, dx: f32, dy: f32) {
+        self.look = (self.look.0 + dx, self.look.1 + dy);
+    }
+
+    /// 取出累积的鼠标移动量并清零.
+    pub(crate) fn take_look(&mut self) -> (f32, f32) {
+        std::mem::take(&mut self.look)
+    }
+
+    /// 累加缩放量.
+    pub(crate) fn zoom(&mut self, steps: f32) {
+        self.zoom += steps;
+    }
+
+    /// 取出累积的缩放量并清零.
+    pub(crate) fn take_zoom(&mut self) -> f32 {
+        std::mem::take(&mut self.zoom)
+    }
+
     pub(crate) fn clear(&mut self) {
         self.actions.clear();
+        self.axes.clear();
+        self.look = (0., 0.);
+        self.zoom = 0.;
     }
 
     pub(crate) fn has_actions(&self) -> bool {
         !self.actions.is_empty()
+            || !self.axes.is_empty()
+            || self.look != (0., 0.)
+            || self.zoom != 0.
     }
 }
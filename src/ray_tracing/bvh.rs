@@ -0,0 +1,326 @@
+use crate::ray_tracing::hittable::Aabb;
+use crate::ray_tracing::vector::Vec3;
+
+/// 层次包围盒 (Bounding Volume Hierarchy), 节点扁平存储在数组中.
+///
+/// 只负责包围盒的遍历, 具体的图元求交由调用者通过闭包完成,
+/// 所以场景物体和网格中的三角形都可以用它来加速.
+#[derive(Debug, Clone, Default)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    /// 图元在调用者那边的下标, 叶子节点引用其中连续的一段.
+    indices: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BvhNode {
+    bounds: Aabb,
+    /// 叶子节点: 第一个图元在 `indices` 中的位置;
+    /// 内部节点: 右子节点的下标, 左子节点紧跟在当前节点后面.
+    offset: usize,
+    /// 叶子节点的图元数量, 内部节点为 0.
+    count: usize,
+    /// 内部节点的划分轴.
+    axis: usize,
+}
+
+impl Bvh {
+    /// SAH 分桶数.
+    const BINS: usize = 12;
+    /// 图元数量不超过这个值时, 如果划分没有收益就直接作为叶子.
+    const MAX_LEAF_SIZE: usize = 4;
+    /// 树的最大深度, 遍历时的栈大小由它决定.
+    const MAX_DEPTH: usize = 63;
+
+    /// 从 (图元下标, 包围盒) 列表构建 BVH, 使用分桶的表面积启发式 (SAH) 选择划分.
+    #[must_use]
+    pub fn build(primitives: Vec<(usize, Aabb)>) -> Self {
+        let mut self_ = Self {
+            nodes: Vec::with_capacity(primitives.len() * 2),
+            indices: Vec::with_capacity(primitives.len()),
+        };
+        if !primitives.is_empty() {
+            let mut primitives = primitives;
+            let len = primitives.len();
+            self_.build_node(&mut primitives, 0, len, 0);
+            self_.indices = primitives.into_iter().map(|(i, _)| i).collect();
+        }
+        self_
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// 整个 BVH 的包围盒.
+    #[must_use]
+    pub fn bounds(&self) -> Option<Aabb> {
+        self.nodes.first().map(|n| n.bounds)
+    }
+
+    fn build_node(
+        &mut self,
+        primitives: &mut [(usize, Aabb)],
+        start: usize,
+        end: usize,
+        depth: usize,
+    ) -> usize {
+        let bounds = primitives[start..end]
+            .iter()
+            .map(|(_, b)| *b)
+            .reduce(Aabb::union)
+            .unwrap();
+        let node_index = self.nodes.len();
+        let count = end - start;
+        self.nodes.push(BvhNode {
+            bounds,
+            offset: start,
+            count,
+            axis: 0,
+        });
+        if count == 1 || depth >= Self::MAX_DEPTH {
+            return node_index;
+        }
+
+        let centroid_bounds = primitives[start..end]
+            .iter()
+            .map(|(_, b)| {
+                let c = b.centroid();
+                Aabb::new(c, c)
+            })
+            .reduce(Aabb::union)
+            .unwrap();
+        let extent = centroid_bounds.max - centroid_bounds.min;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+        let axis_min = component(centroid_bounds.min, axis);
+        let axis_extent = component(extent, axis);
+        if axis_extent <= f32::EPSILON {
+            // 所有图元中心重合, 没法划分.
+            return node_index;
+        }
+
+        let bin_of = |b: &Aabb| {
+            let offset = (component(b.centroid(), axis) - axis_min) / axis_extent;
+            ((offset * Self::BINS as f32) as usize).min(Self::BINS - 1)
+        };
+        let mut bins: [(Option<Aabb>, usize); Self::BINS] = [(None, 0); Self::BINS];
+        for (_, b) in &primitives[start..end] {
+            let bin = &mut bins[bin_of(b)];
+            bin.0 = Some(bin.0.map_or(*b, |bb| bb.union(*b)));
+            bin.1 += 1;
+        }
+        // 对每个划分位置计算代价: 左右两边的表面积 * 图元数量.
+        let mut best_split = 0;
+        let mut best_cost = f32::INFINITY;
+        for split in 1..Self::BINS {
+            let side = |range: &[(Option<Aabb>, usize)]| {
+                let count = range.iter().map(|(_, c)| c).sum::<usize>();
+                let area = range
+                    .iter()
+                    .filter_map(|(b, _)| *b)
+                    .reduce(Aabb::union)
+                    .map_or(0.0, Aabb::surface_area);
+                area * count as f32
+            };
+            let cost = side(&bins[..split]) + side(&bins[split..]);
+            if cost < best_cost {
+                best_cost = cost;
+                best_split = split;
+            }
+        }
+        let leaf_cost = bounds.surface_area() * count as f32;
+        if count <= Self::MAX_LEAF_SIZE && best_cost >= leaf_cost {
+            return node_index;
+        }
+
+        // 按照桶划分图元.
+        let range = &mut primitives[start..end];
+        let mut mid = 0;
+        for i in 0..range.len() {
+            if bin_of(&range[i].1) < best_split {
+                range.swap(i, mid);
+                mid += 1;
+            }
+        }
+        if mid == 0 || mid == range.len() {
+            // 划分失败, 退化为按中心排序后取中位数.
+            range.sort_by(|(_, a), (_, b)| {
+                component(a.centroid(), axis).total_cmp(&component(b.centroid(), axis))
+            });
+            mid = range.len() / 2;
+        }
+        let mid = start + mid;
+
+        self.build_node(primitives, start, mid, depth + 1);
+        let right = self.build_node(primitives, mid, end, depth + 1);
+        self.nodes[node_index] = BvhNode {
+            bounds,
+            offset: right,
+            count: 0,
+            axis,
+        };
+        node_index
+    }
+
+    /// 最近相交查询.
+    ///
+    /// `hit` 对一个图元 (调用者那边的下标) 求交,
+    /// 如果相交距离小于当前的最近距离则返回这个距离, 之后的遍历会用它剪枝.
+    pub fn closest_hit(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+        mut hit: impl FnMut(usize, f32) -> Option<f32>,
+    ) {
+        let mut max_distance = max_distance;
+        self.traverse(origin, direction, &mut max_distance, |i, max_distance| {
+            if let Some(t) = hit(i, *max_distance) {
+                *max_distance = t;
+            }
+            false
+        });
+    }
+
+    /// 任意相交查询, `hit` 返回 true 时立刻停止遍历并返回 true.
+    pub fn any_hit(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+        mut hit: impl FnMut(usize) -> bool,
+    ) -> bool {
+        let mut max_distance = max_distance;
+        self.traverse(origin, direction, &mut max_distance, |i, _| hit(i))
+    }
+
+    /// 遍历和光线相交的叶子, 先访问离光线起点近的子节点.
+    /// `visit` 返回 true 时提前结束遍历.
+    fn traverse(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: &mut f32,
+        mut visit: impl FnMut(usize, &mut f32) -> bool,
+    ) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+        let inv_direction = Vec3::new(1.0 / direction.x, 1.0 / direction.y, 1.0 / direction.z);
+        // 固定大小的栈, 避免每条光线都分配内存.
+        // 每层最多留下一个待访问的兄弟节点, 所以深度 + 1 就够了.
+        let mut stack = [0usize; Self::MAX_DEPTH + 1];
+        let mut len = 1;
+        while len > 0 {
+            len -= 1;
+            let node_index = stack[len];
+            let node = &self.nodes[node_index];
+            if !node.bounds.hit(origin, inv_direction, *max_distance) {
+                continue;
+            }
+            if node.count > 0 {
+                for &i in &self.indices[node.offset..node.offset + node.count] {
+                    if visit(i, max_distance) {
+                        return true;
+                    }
+                }
+            } else {
+                // 先访问光线方向上更近的子节点.
+                let (near, far) = if component(direction, node.axis) < 0.0 {
+                    (node.offset, node_index + 1)
+                } else {
+                    (node_index + 1, node.offset)
+                };
+                stack[len] = far;
+                stack[len + 1] = near;
+                len += 2;
+            }
+        }
+        false
+    }
+}
+
+fn component(v: Vec3, axis: usize) -> f32 {
+    match axis {
+        0 => v.x,
+        1 => v.y,
+        _ => v.z,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::ray_tracing::Sphere;
+    use crate::ray_tracing::hittable::Hittable;
+
+    fn random_vec(rng: &mut SmallRng, scale: f32) -> Vec3 {
+        Vec3::new(
+            rng.random_range(-scale..scale),
+            rng.random_range(-scale..scale),
+            rng.random_range(-scale..scale),
+        )
+    }
+
+    fn build(spheres: &[Sphere]) -> Bvh {
+        Bvh::build(
+            spheres
+                .iter()
+                .enumerate()
+                .map(|(i, s)| (i, s.bounding_box().unwrap()))
+                .collect(),
+        )
+    }
+
+    /// 对每条光线比较 BVH 和逐个检测的最近交点.
+    fn assert_matches_brute_force(spheres: &[Sphere], rng: &mut SmallRng) {
+        let bvh = build(spheres);
+        for _ in 0..500 {
+            let origin = random_vec(rng, 12.);
+            let direction = random_vec(rng, 1.).normalize();
+            let max_distance = rng.random_range(1.0..30.0);
+
+            let brute_force = spheres
+                .iter()
+                .enumerate()
+                .filter_map(|(i, s)| {
+                    Some((i, Hittable::intersect(s, origin, direction, max_distance)?))
+                })
+                .min_by(|(_, a), (_, b)| a.distance().total_cmp(&b.distance()))
+                .map(|(i, _)| i);
+
+            let mut closest = None;
+            bvh.closest_hit(origin, direction, max_distance, |i, max_distance| {
+                let t =
+                    Hittable::intersect(&spheres[i], origin, direction, max_distance)?.distance();
+                closest = Some(i);
+                Some(t)
+            });
+            assert_eq!(closest, brute_force);
+
+            let any = bvh.any_hit(origin, direction, max_distance, |i| {
+                spheres[i].occludes(origin, direction, max_distance)
+            });
+            assert_eq!(any, brute_force.is_some());
+        }
+    }
+
+    #[test]
+    fn closest_hit_matches_brute_force() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let spheres: Vec<_> = (0..200)
+            .map(|_| Sphere::new(random_vec(&mut rng, 10.), rng.random_range(0.1..1.0)))
+            .collect();
+        assert_matches_brute_force(&spheres, &mut rng);
+    }
+}
@@ -0,0 +1,255 @@
+use std::f32;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::ray_tracing::vector::Vec3;
+
+/// 投影方式, 决定每个像素对应哪一条光线.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Projection {
+    /// 透视投影, 近大远小.
+    Perspective,
+    /// 正交投影, 所有光线平行于视线, 适合技术视图.
+    Orthographic,
+    /// 等距鱼眼: 到画面中心的距离和光线偏离视线的角度成正比, 超过 180 度的部分是黑色.
+    Fisheye,
+    /// 等距柱状全景 (360° x 180°), 可以直接作为环境贴图.
+    Equirectangular,
+}
+
+/// 相机.
+///
+/// 视场角是竖直方向的, 水平方向按照画面宽高比展开, 所以非正方形的画面不会被拉伸.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy)]
+pub struct Camera {
+    /// 相机世界坐标.
+    pos: Vec3,
+    /// 视线方向, 始终是标准化的.
+    gaze: Vec3,
+    /// 相机的上方向, 始终是标准化的, 不需要和视线垂直.
+    up: Vec3,
+    /// 绕视线的旋转角 (角度制), 正值使画面顺时针旋转.
+    roll: f32,
+    /// 竖直视场角 (角度制), 透视投影和鱼眼投影使用.
+    fov: f32,
+    /// 投影方式.
+    projection: Projection,
+    /// 正交投影时画面的高度 (米).
+    ortho_height: f32,
+}
+
+/// 视平面.
+///
+/// 透视投影时位于相机前方 1 米处, 正交投影时穿过相机.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ViewPlane {
+    /// 从相机指向视平面左上角的向量.
+    pub(crate) top_left: Vec3,
+    /// 画面宽高比 (宽 / 高).
+    pub(crate) aspect: f32,
+    /// 视平面从左到右的整个宽度.
+    pub(crate) horizontal: Vec3,
+    /// 视平面从上到下的整个高度.
+    pub(crate) vertical: Vec3,
+    /// 画面右方向 (标准化).
+    pub(crate) right: Vec3,
+    /// 画面下方向 (标准化).
+    pub(crate) down: Vec3,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new(Vec3::ZERO, Vec3::X)
+    }
+}
+
+#[wasm_bindgen]
+impl Camera {
+    /// 位于 `pos`, 看向 `gaze` 方向, 以 z 轴为上方向的相机.
+    #[must_use]
+    pub fn new(pos: Vec3, gaze: Vec3) -> Self {
+        Self {
+            pos,
+            gaze: gaze.normalize(),
+            up: Vec3::Z,
+            roll: 0.,
+            fov: Self::DEFAULT_FOV,
+            projection: Projection::Perspective,
+            ortho_height: 10.,
+        }
+    }
+
+    /// 位于 `eye`, 看向 `target` 点的相机.
+    #[must_use]
+    pub fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Self {
+        Self {
+            up: up.normalize(),
+            ..Self::new(eye, target - eye)
+        }
+    }
+
+    #[must_use]
+    pub fn pos(&self) -> Vec3 {
+        self.pos
+    }
+
+    pub fn set_pos(&mut self, pos: Vec3) {
+        self.pos = pos;
+    }
+
+    #[must_use]
+    pub fn gaze(&self) -> Vec3 {
+        self.gaze
+    }
+
+    pub fn set_gaze(&mut self, gaze: Vec3) {
+        self.gaze = gaze.normalize();
+    }
+
+    #[must_use]
+    pub fn up(&self) -> Vec3 {
+        self.up
+    }
+
+    pub fn set_up(&mut self, up: Vec3) {
+        self.up = up.normalize();
+    }
+
+    #[must_use]
+    pub fn roll(&self) -> f32 {
+        self.roll
+    }
+
+    pub fn set_roll(&mut self, roll: f32) {
+        self.roll = roll;
+    }
+
+    #[must_use]
+    pub fn fov(&self) -> f32 {
+        self.fov
+    }
+
+    /// 设置竖直视场角 (角度制), 限制在 1 ~ 179 度.
+    pub fn set_fov(&mut self, fov: f32) {
+        self.fov = fov.clamp(1., 179.);
+    }
+
+    #[must_use]
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+    }
+
+    #[must_use]
+    pub fn ortho_height(&self) -> f32 {
+        self.ortho_height
+    }
+
+    /// 设置正交投影时画面的高度 (米).
+    pub fn set_ortho_height(&mut self, height: f32) {
+        self.ortho_height = height.max(f32::EPSILON);
+    }
+}
+
+impl Camera {
+    /// 默认的竖直视场角 (角度制).
+    const DEFAULT_FOV: f32 = 100.;
+
+    /// 画面的右方向和下方向 (标准化, 已经算上 roll).
+    ///
+    /// 视线和上方向平行时换一个辅助方向, 避免叉积为零.
+    #[must_use]
+    pub fn basis(&self) -> (Vec3, Vec3) {
+        let mut right = self.up.cross(self.gaze);
+        if right.is_zero() {
+            let helper = if self.gaze.x.abs() > 0.9 {
+                Vec3::Y
+            } else {
+                Vec3::X
+            };
+            right = helper.cross(self.gaze);
+        }
+        let right = right.normalize();
+        let down = right.cross(self.gaze);
+        let (sin, cos) = self.roll.to_radians().sin_cos();
+        (right * cos - down * sin, down * cos + right * sin)
+    }
+
+    /// 宽高比为 `aspect` (宽 / 高) 的画面对应的视平面.
+    pub(crate) fn view_plane(&self, aspect: f32) -> ViewPlane {
+        let (right, down) = self.basis();
+        let (height, center) = match self.projection {
+            Projection::Orthographic => (self.ortho_height, Vec3::ZERO),
+            _ => (2.0 * (self.fov.to_radians() / 2.0).tan(), self.gaze),
+        };
+        let horizontal = right * (height * aspect);
+        let vertical = down * height;
+        ViewPlane {
+            top_left: center - horizontal * 0.5 - vertical * 0.5,
+            aspect,
+            horizontal,
+            vertical,
+            right,
+            down,
+        }
+    }
+
+    /// 画面上 `(rx, ry)` (0.0 ~ 1.0, 从左上角开始) 处的光线, 返回 (起点, 标准化方向).
+    ///
+    /// 鱼眼投影超出 180 度的部分没有光线, 返回 None.
+    pub(crate) fn primary_ray(&self, view: &ViewPlane, rx: f32, ry: f32) -> Option<(Vec3, Vec3)> {
+        match self.projection {
+            Projection::Perspective => Some((
+                self.pos,
+                (view.top_left + view.horizontal * rx + view.vertical * ry).normalize(),
+            )),
+            Projection::Orthographic => Some((
+                self.pos + view.top_left + view.horizontal * rx + view.vertical * ry,
+                self.gaze,
+            )),
+            Projection::Fisheye => {
+                // 以画面高度为单位的坐标, 画面上下边缘对应半个视场角.
+                let x = (rx - 0.5) * view.aspect;
+                let y = ry - 0.5;
+                let theta = (x * x + y * y).sqrt() * self.fov.to_radians();
+                if theta > f32::consts::PI {
+                    return None;
+                }
+                let phi = y.atan2(x);
+                let (sin_theta, cos_theta) = theta.sin_cos();
+                let (sin_phi, cos_phi) = phi.sin_cos();
+                Some((
+                    self.pos,
+                    (self.gaze * cos_theta
+                        + (view.right * cos_phi + view.down * sin_phi) * sin_theta)
+                        .normalize(),
+                ))
+            }
+            Projection::Equirectangular => {
+                // 全景以上方向为天顶, 不受 roll 影响, 画面中心是视线的水平方向.
+                let mut forward = self.gaze - self.up * self.gaze.dot(self.up);
+                if forward.is_zero() {
+                    forward = -view.down;
+                }
+                let forward = forward.normalize();
+                let right = self.up.cross(forward);
+                let longitude = (rx - 0.5) * 2.0 * f32::consts::PI;
+                let latitude = (0.5 - ry) * f32::consts::PI;
+                let (sin_lon, cos_lon) = longitude.sin_cos();
+                let (sin_lat, cos_lat) = latitude.sin_cos();
+                Some((
+                    self.pos,
+                    ((forward * cos_lon + right * sin_lon) * cos_lat + self.up * sin_lat)
+                        .normalize(),
+                ))
+            }
+        }
+    }
+}
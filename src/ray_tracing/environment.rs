@@ -0,0 +1,291 @@
+use std::f32;
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::ray_tracing::vector::Vec3;
+
+/// 等距柱状 (equirectangular) 环境贴图, 作为背景和光源.
+///
+/// 贴图中心对应 +x 方向, 向右是 +y 方向, 上边缘是 +z 方向,
+/// 和 [`Projection::Equirectangular`](super::camera::Projection::Equirectangular)
+/// 从 +x 方向拍出来的全景一致.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    /// 线性颜色, 按行存储, 从左上角开始.
+    pixels: Vec<Vec3>,
+    /// 绕 z 轴的旋转角 (角度制).
+    rotation: f32,
+}
+
+#[wasm_bindgen]
+impl EnvironmentMap {
+    /// 解析 Radiance HDR (.hdr, RGBE) 图片.
+    ///
+    /// 支持未压缩和新式游程编码 (RLE) 的扫描线, 只支持 `-Y h +X w` 方向.
+    pub fn from_hdr(bytes: &[u8]) -> Result<EnvironmentMap, String> {
+        let mut rest = bytes;
+        let mut next_line = || -> Result<&str, String> {
+            let current = rest;
+            let end = current
+                .iter()
+                .position(|&b| b == b'\n')
+                .ok_or("hdr: unexpected end of header")?;
+            let line = std::str::from_utf8(&current[..end])
+                .map_err(|e| format!("hdr: header is not utf-8: {e}"))?;
+            rest = &current[end + 1..];
+            Ok(line.trim_end_matches('\r'))
+        };
+
+        if !next_line()?.starts_with("#?") {
+            return Err("hdr: missing #? signature".into());
+        }
+        loop {
+            let line = next_line()?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some(format) = line.strip_prefix("FORMAT=")
+                && format != "32-bit_rle_rgbe"
+            {
+                return Err(format!("hdr: unsupported format {format:?}"));
+            }
+        }
+        let resolution = next_line()?;
+        let (height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+            ["-Y", height, "+X", width] => (
+                height
+                    .parse::<usize>()
+                    .map_err(|e| format!("hdr: invalid height {height:?}: {e}"))?,
+                width
+                    .parse::<usize>()
+                    .map_err(|e| format!("hdr: invalid width {width:?}: {e}"))?,
+            ),
+            _ => return Err(format!("hdr: unsupported resolution line {resolution:?}")),
+        };
+        if width == 0 || height == 0 {
+            return Err("hdr: empty image".into());
+        }
+
+        // 先根据剩下的字节数检查尺寸, 避免按照错误的文件头分配巨大的内存.
+        let pixel_count = width.checked_mul(height);
+        let min_bytes = min_scanline_bytes(width).checked_mul(height);
+        let (Some(pixel_count), Some(min_bytes)) = (pixel_count, min_bytes) else {
+            return Err(format!("hdr: image too large ({width}x{height})"));
+        };
+        if rest.len() < min_bytes {
+            return Err("hdr: unexpected end of pixel data".into());
+        }
+
+        let mut pixels = Vec::with_capacity(pixel_count);
+        let mut scanline = vec![[0u8; 4]; width];
+        for _ in 0..height {
+            rest = read_scanline(rest, &mut scanline)?;
+            pixels.extend(scanline.iter().map(|&rgbe| rgbe_to_vec3(rgbe)));
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels,
+            rotation: 0.,
+        })
+    }
+
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    #[must_use]
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    /// 设置绕 z 轴的旋转角 (角度制), 正值使环境逆时针 (俯视) 旋转.
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.rotation = rotation;
+    }
+}
+
+impl EnvironmentMap {
+    /// 沿 `direction` (标准化) 看到的颜色, 双线性插值.
+    #[must_use]
+    pub fn sample(&self, direction: Vec3) -> Vec3 {
+        let longitude = direction.y.atan2(direction.x) - self.rotation.to_radians();
+        let latitude = direction.z.clamp(-1.0, 1.0).asin();
+        let u = (0.5 + longitude / (2.0 * f32::consts::PI)).rem_euclid(1.0);
+        let v = 0.5 - latitude / f32::consts::PI;
+
+        // 像素中心在 (i + 0.5) / size 处.
+        let x = u * self.width as f32 - 0.5;
+        let y = (v * self.height as f32 - 0.5).clamp(0.0, (self.height - 1) as f32);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        // 水平方向首尾相接.
+        let x0 = (x0 as isize).rem_euclid(self.width as isize) as usize;
+        let x1 = (x0 + 1) % self.width;
+        let y0 = y0 as usize;
+        let y1 = (y0 + 1).min(self.height - 1);
+        let pixel = |x: usize, y: usize| self.pixels[y * self.width + x];
+        let top = pixel(x0, y0) * (1.0 - tx) + pixel(x1, y0) * tx;
+        let bottom = pixel(x0, y1) * (1.0 - tx) + pixel(x1, y1) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+}
+
+/// 宽度在这个范围内的扫描线才可能使用 RLE.
+fn rle_width(width: usize) -> bool {
+    (8..0x8000).contains(&width)
+}
+
+/// 一条扫描线至少占用的字节数: RLE 每个通道的一段最多 127 个像素, 占 2 个字节.
+fn min_scanline_bytes(width: usize) -> usize {
+    if rle_width(width) {
+        4 + 4 * 2 * width.div_ceil(127)
+    } else {
+        4 * width
+    }
+}
+
+/// 读取一条扫描线到 `scanline`, 返回剩下的字节.
+fn read_scanline<'a>(bytes: &'a [u8], scanline: &mut [[u8; 4]]) -> Result<&'a [u8], String> {
+    let width = scanline.len();
+    let truncated = || "hdr: unexpected end of pixel data".to_string();
+    let is_rle = rle_width(width)
+        && bytes.len() >= 4
+        && bytes[0] == 2
+        && bytes[1] == 2
+        && bytes[2] & 0x80 == 0;
+    if !is_rle {
+        // 未压缩, 每个像素 4 个字节.
+        let data = bytes.get(..width * 4).ok_or_else(truncated)?;
+        for (pixel, rgbe) in scanline.iter_mut().zip(data.chunks_exact(4)) {
+            pixel.copy_from_slice(rgbe);
+        }
+        return Ok(&bytes[width * 4..]);
+    }
+    if (usize::from(bytes[2]) << 8 | usize::from(bytes[3])) != width {
+        return Err("hdr: scanline width mismatch".into());
+    }
+
+    // 新式 RLE: 四个通道分别编码.
+    let mut rest = &bytes[4..];
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let (&count, tail) = rest.split_first().ok_or_else(truncated)?;
+            if count > 128 {
+                // 一段重复的值.
+                let count = usize::from(count - 128);
+                let (&value, tail) = tail.split_first().ok_or_else(truncated)?;
+                let run = scanline
+                    .get_mut(x..x + count)
+                    .ok_or("hdr: run exceeds scanline")?;
+                run.iter_mut().for_each(|p| p[channel] = value);
+                x += count;
+                rest = tail;
+            } else {
+                // 一段原样存储的值.
+                let count = usize::from(count);
+                if count == 0 {
+                    return Err("hdr: zero-length run".into());
+                }
+                let values = tail.get(..count).ok_or_else(truncated)?;
+                let run = scanline
+                    .get_mut(x..x + count)
+                    .ok_or("hdr: run exceeds scanline")?;
+                run.iter_mut()
+                    .zip(values)
+                    .for_each(|(p, &value)| p[channel] = value);
+                x += count;
+                rest = &tail[count..];
+            }
+        }
+    }
+    Ok(rest)
+}
+
+/// RGBE 转线性颜色: 三个通道共用一个指数.
+fn rgbe_to_vec3([r, g, b, e]: [u8; 4]) -> Vec3 {
+    if e == 0 {
+        return Vec3::ZERO;
+    }
+    let scale = 2f32.powi(i32::from(e) - (128 + 8));
+    Vec3::new(
+        f32::from(r) * scale,
+        f32::from(g) * scale,
+        f32::from(b) * scale,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray_tracing::vector::approx_eq;
+
+    fn hdr(resolution: &str, pixels: &[u8]) -> Vec<u8> {
+        let mut bytes =
+            format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n{resolution}\n").into_bytes();
+        bytes.extend_from_slice(pixels);
+        bytes
+    }
+
+    #[test]
+    fn reads_flat_scanlines() {
+        // 指数 129 对应 1 / 128.
+        let pixels = [128, 64, 0, 129, 0, 0, 0, 0, 32, 32, 32, 130, 0, 0, 128, 129];
+        let map = EnvironmentMap::from_hdr(&hdr("-Y 2 +X 2", &pixels)).unwrap();
+        assert_eq!((map.width(), map.height()), (2, 2));
+        assert!(approx_eq(map.pixels[0], Vec3::new(1., 0.5, 0.)));
+        assert!(approx_eq(map.pixels[1], Vec3::ZERO));
+        assert!(approx_eq(map.pixels[2], Vec3::new(0.5, 0.5, 0.5)));
+        assert!(approx_eq(map.pixels[3], Vec3::new(0., 0., 1.)));
+    }
+
+    #[test]
+    fn reads_rle_scanlines() {
+        #[rustfmt::skip]
+        let pixels = [
+            2, 2, 0, 8,
+            // r: 8 个重复的 128.
+            128 + 8, 128,
+            // g: 8 个原样存储的值.
+            8, 0, 16, 32, 48, 64, 80, 96, 112,
+            // b: 两段重复.
+            128 + 3, 64, 128 + 5, 0,
+            // e: 8 个重复的 129.
+            128 + 8, 129,
+        ];
+        let map = EnvironmentMap::from_hdr(&hdr("-Y 1 +X 8", &pixels)).unwrap();
+        assert_eq!(map.pixels.len(), 8);
+        for (x, pixel) in map.pixels.iter().enumerate() {
+            let b = if x < 3 { 0.5 } else { 0. };
+            assert!(
+                approx_eq(*pixel, Vec3::new(1., x as f32 * 0.125, b)),
+                "{x}: {pixel:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_bad_headers_and_data() {
+        assert!(EnvironmentMap::from_hdr(b"P6\n").is_err());
+        assert!(EnvironmentMap::from_hdr(&hdr("+Y 1 +X 1", &[0; 4])).is_err());
+        // 文件头声称的尺寸远大于实际数据, 不会按照它分配内存.
+        let error = EnvironmentMap::from_hdr(&hdr("-Y 100000 +X 100000", &[0; 16])).unwrap_err();
+        assert!(error.contains("unexpected end"), "{error}");
+        let error =
+            EnvironmentMap::from_hdr(&hdr(&format!("-Y {} +X 2", usize::MAX), &[])).unwrap_err();
+        assert!(error.contains("too large"), "{error}");
+        // RLE 的一段超出扫描线.
+        let pixels = [2, 2, 0, 8, 128 + 9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert!(EnvironmentMap::from_hdr(&hdr("-Y 1 +X 8", &pixels)).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+use std::io::Cursor;
+use std::path::Path;
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::ray_tracing::vector::Vec3;
+
+/// 导出图片的格式.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// 二进制 PPM (P6), 和 README 中参考代码的输出格式一致.
+    Ppm,
+    /// 8 位 sRGB PNG.
+    Png,
+    /// 32 位浮点线性颜色的 OpenEXR, 没有经过曝光和色调映射, 用于后期合成.
+    Exr,
+}
+
+impl ImageFormat {
+    /// 根据文件扩展名 (不区分大小写) 判断格式.
+    #[must_use]
+    pub fn from_extension(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "ppm" => Some(Self::Ppm),
+            "png" => Some(Self::Png),
+            "exr" => Some(Self::Exr),
+            _ => None,
+        }
+    }
+}
+
+/// `0xAARRGGBB` 像素按行排列的 rgb 字节.
+fn rgb_bytes(pixels: &[u32]) -> Vec<u8> {
+    pixels
+        .iter()
+        .flat_map(|&p| [(p >> 16) as u8, (p >> 8) as u8, p as u8])
+        .collect()
+}
+
+/// 编码成二进制 PPM, `pixels` 是按行排列的 `0xAARRGGBB`.
+#[must_use]
+pub fn encode_ppm(width: usize, height: usize, pixels: &[u32]) -> Vec<u8> {
+    let mut bytes = format!("P6 {width} {height} 255\n").into_bytes();
+    bytes.extend(rgb_bytes(pixels));
+    bytes
+}
+
+/// 编码成 PNG, `pixels` 是按行排列的 `0xAARRGGBB`.
+pub fn encode_png(width: usize, height: usize, pixels: &[u32]) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let width = u32::try_from(width).map_err(|e| format!("png: width too large: {e}"))?;
+    let height = u32::try_from(height).map_err(|e| format!("png: height too large: {e}"))?;
+    let mut encoder = png::Encoder::new(&mut bytes, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    let mut writer = encoder.write_header().map_err(|e| format!("png: {e}"))?;
+    writer
+        .write_image_data(&rgb_bytes(pixels))
+        .map_err(|e| format!("png: {e}"))?;
+    writer.finish().map_err(|e| format!("png: {e}"))?;
+    Ok(bytes)
+}
+
+/// 编码成 OpenEXR, `pixels` 是按行排列的线性颜色.
+pub fn encode_exr(width: usize, height: usize, pixels: &[Vec3]) -> Result<Vec<u8>, String> {
+    use exr::prelude::{Image, SpecificChannels, Vec2, WritableImage};
+
+    let channels = SpecificChannels::rgb(|position: Vec2<usize>| {
+        let color = pixels[position.y() * width + position.x()];
+        (color.x, color.y, color.z)
+    });
+    let mut bytes = Cursor::new(Vec::new());
+    Image::from_channels((width, height), channels)
+        .write()
+        .non_parallel()
+        .to_buffered(&mut bytes)
+        .map_err(|e| format!("exr: {e}"))?;
+    Ok(bytes.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PIXELS: [u32; 2] = [0xff10_2030, 0xffff_8000];
+
+    #[test]
+    fn ppm_header_and_data() {
+        let bytes = encode_ppm(2, 1, &PIXELS);
+        assert_eq!(bytes, b"P6 2 1 255\n\x10\x20\x30\xff\x80\x00");
+    }
+
+    #[test]
+    fn png_header() {
+        let bytes = encode_png(2, 1, &PIXELS).unwrap();
+        assert_eq!(bytes[..8], *b"\x89PNG\r\n\x1a\n");
+        // IHDR: 宽, 高, 位深 8, rgb.
+        assert_eq!(bytes[12..16], *b"IHDR");
+        assert_eq!(bytes[16..26], [0, 0, 0, 2, 0, 0, 0, 1, 8, 2]);
+    }
+
+    #[test]
+    fn exr_header_and_data() {
+        use exr::prelude::{ReadChannels, ReadLayers, read};
+
+        let pixels = [Vec3::new(0.5, 2., 100.), Vec3::new(0., 0.25, 1.)];
+        let bytes = encode_exr(2, 1, &pixels).unwrap();
+        // 魔数和版本号 2.
+        assert_eq!(bytes[..5], [0x76, 0x2f, 0x31, 0x01, 2]);
+
+        let image = read()
+            .no_deep_data()
+            .largest_resolution_level()
+            .rgb_channels(
+                |resolution, _| vec![(0f32, 0f32, 0f32); resolution.width() * resolution.height()],
+                |buffer, position, (r, g, b): (f32, f32, f32)| {
+                    buffer[position.y() * 2 + position.x()] = (r, g, b);
+                },
+            )
+            .first_valid_layer()
+            .all_attributes()
+            .from_buffered(Cursor::new(bytes))
+            .unwrap();
+        assert_eq!(
+            image.layer_data.channel_data.pixels,
+            [(0.5, 2., 100.), (0., 0.25, 1.)]
+        );
+    }
+}
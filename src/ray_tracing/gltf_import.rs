@@ -0,0 +1,462 @@
+use std::path::Path;
+
+use base64::Engine;
+use gltf::khr_lights_punctual::Kind;
+use gltf::mesh::Mode;
+
+use crate::ray_tracing::camera::Projection;
+use crate::ray_tracing::scene::{
+    CameraDesc, LightDesc, LightShape, MaterialDesc, MeshDesc, ObjectDesc,
+};
+use crate::ray_tracing::vector::Vec3;
+
+/// 列主序的 4x4 变换矩阵, 和 glTF 一致.
+type Mat4 = [[f32; 4]; 4];
+
+const IDENTITY: Mat4 = [
+    [1., 0., 0., 0.],
+    [0., 1., 0., 0.],
+    [0., 0., 1., 0.],
+    [0., 0., 0., 1.],
+];
+
+/// 从 glTF 2.0 文件 (.gltf 或 .glb) 导入的场景内容.
+///
+/// - 每个网格图元 (primitive) 变成一个 [`Mesh`](super::mesh::Mesh), 节点层级的变换已经应用到顶点上.
+/// - PBR metallic-roughness 材质映射到最接近的 [`Material`](super::material::Material):
+///   有自发光的是自发光材质, 有透射 (`KHR_materials_transmission`) 的是电介质,
+///   金属度不小于 0.5 的是金属, 其余是漫反射. 纹理和粗糙度会被忽略.
+/// - `KHR_lights_punctual` 的点光源, 聚光灯和平行光.
+/// - 场景中的第一个相机.
+///
+/// glTF 的 y 轴向上, 导入时会转换到本项目 z 轴向上的坐标系.
+#[derive(Debug, Clone, Default)]
+pub struct GltfScene {
+    pub objects: Vec<ObjectDesc>,
+    pub lights: Vec<LightDesc>,
+    pub camera: Option<CameraDesc>,
+}
+
+impl GltfScene {
+    /// 解析 glTF 文件内容.
+    ///
+    /// 二进制 glTF 的 BIN 块和 `data:` URI 中的缓冲区可以直接读取,
+    /// 外部文件 (比如 `.bin`) 通过 `load_uri` 按 URI 加载.
+    pub fn parse(
+        bytes: &[u8],
+        mut load_uri: impl FnMut(&str) -> Result<Vec<u8>, String>,
+    ) -> Result<Self, String> {
+        let gltf = gltf::Gltf::from_slice(bytes).map_err(|e| format!("gltf: {e}"))?;
+        let mut blob = gltf.blob;
+        let buffers = gltf
+            .document
+            .buffers()
+            .map(|buffer| {
+                let data = match buffer.source() {
+                    gltf::buffer::Source::Bin => blob
+                        .take()
+                        .ok_or_else(|| "gltf: missing binary chunk".to_string())?,
+                    gltf::buffer::Source::Uri(uri) => match uri.strip_prefix("data:") {
+                        Some(data_uri) => decode_data_uri(data_uri)?,
+                        None => load_uri(uri)?,
+                    },
+                };
+                if data.len() < buffer.length() {
+                    return Err(format!("gltf: buffer {} is too short", buffer.index()));
+                }
+                Ok(data)
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let scene = gltf
+            .document
+            .default_scene()
+            .or_else(|| gltf.document.scenes().next())
+            .ok_or_else(|| "gltf: no scene".to_string())?;
+        let mut imported = Self::default();
+        for node in scene.nodes() {
+            imported.visit(&node, &IDENTITY, &buffers)?;
+        }
+        Ok(imported)
+    }
+
+    /// 读取 glTF 文件, 外部缓冲区文件相对 glTF 文件所在的目录查找.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+        Self::parse(&bytes, |uri| {
+            let path = dir.join(percent_decode(uri)?);
+            std::fs::read(&path).map_err(|e| format!("{}: {e}", path.display()))
+        })
+    }
+
+    /// 导入一个节点和它的子节点, `parent` 是父节点的世界变换.
+    fn visit(
+        &mut self,
+        node: &gltf::Node,
+        parent: &Mat4,
+        buffers: &[Vec<u8>],
+    ) -> Result<(), String> {
+        let transform = mul(parent, &node.transform().matrix());
+        if let Some(mesh) = node.mesh() {
+            for primitive in mesh.primitives() {
+                if let Some(mesh) = import_primitive(&primitive, &transform, buffers)? {
+                    self.objects.push(ObjectDesc::Mesh(mesh));
+                }
+            }
+        }
+        if let Some(light) = node.light() {
+            self.lights.push(import_light(&light, &transform));
+        }
+        if let Some(camera) = node.camera()
+            && self.camera.is_none()
+        {
+            self.camera = Some(import_camera(&camera, &transform));
+        }
+        for child in node.children() {
+            self.visit(&child, &transform, buffers)?;
+        }
+        Ok(())
+    }
+}
+
+/// 解析 `data:` 之后的部分, 只支持 base64 编码.
+fn decode_data_uri(data_uri: &str) -> Result<Vec<u8>, String> {
+    let (_, data) = data_uri
+        .split_once(";base64,")
+        .ok_or_else(|| "gltf: only base64 data uri is supported".to_string())?;
+    base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .map_err(|e| format!("gltf: invalid data uri: {e}"))
+}
+
+/// 解码 URI 中的 `%XX` 转义, 比如空格 `%20`.
+fn percent_decode(uri: &str) -> Result<String, String> {
+    let mut bytes = Vec::with_capacity(uri.len());
+    let mut rest = uri.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail
+                .get(..2)
+                .and_then(|h| std::str::from_utf8(h).ok())
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or_else(|| format!("gltf: invalid uri {uri:?}"))?;
+            bytes.push(hex);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).map_err(|e| format!("gltf: invalid uri {uri:?}: {e}"))
+}
+
+fn mul(a: &Mat4, b: &Mat4) -> Mat4 {
+    let mut m = [[0.; 4]; 4];
+    for (col, b_col) in m.iter_mut().zip(b) {
+        for (row, value) in col.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b_col[k]).sum();
+        }
+    }
+    m
+}
+
+/// 矩阵左上角 3x3 的三列, 也就是三个坐标轴变换之后的方向.
+fn axes(m: &Mat4) -> [Vec3; 3] {
+    [0, 1, 2].map(|i| Vec3::new(m[i][0], m[i][1], m[i][2]))
+}
+
+fn transform_vector(m: &Mat4, [x, y, z]: [f32; 3]) -> Vec3 {
+    let [a, b, c] = axes(m);
+    a * x + b * y + c * z
+}
+
+fn transform_point(m: &Mat4, p: [f32; 3]) -> Vec3 {
+    transform_vector(m, p) + Vec3::new(m[3][0], m[3][1], m[3][2])
+}
+
+/// glTF 的 y 轴向上坐标转换到 z 轴向上, 和 [`Mesh::y_up_to_z_up`](super::mesh::Mesh::y_up_to_z_up) 一致.
+fn z_up(v: Vec3) -> Vec3 {
+    Vec3::new(v.x, -v.z, v.y)
+}
+
+fn import_primitive(
+    primitive: &gltf::Primitive,
+    transform: &Mat4,
+    buffers: &[Vec<u8>],
+) -> Result<Option<MeshDesc>, String> {
+    let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+    let positions = reader
+        .read_positions()
+        .ok_or_else(|| "gltf: primitive has no positions".to_string())?
+        .collect::<Vec<_>>();
+    let indices = match reader.read_indices() {
+        Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+        None => (0..positions.len()).collect::<Vec<_>>(),
+    };
+    let mut triangles = match primitive.mode() {
+        Mode::Triangles => indices
+            .chunks_exact(3)
+            .map(|t| [t[0], t[1], t[2]])
+            .collect::<Vec<_>>(),
+        // 条带中奇数位置的三角形顶点顺序是反的.
+        Mode::TriangleStrip => indices
+            .windows(3)
+            .enumerate()
+            .map(|(i, t)| {
+                if i % 2 == 0 {
+                    [t[0], t[1], t[2]]
+                } else {
+                    [t[1], t[0], t[2]]
+                }
+            })
+            .collect(),
+        Mode::TriangleFan => indices
+            .windows(2)
+            .skip(1)
+            .map(|t| [indices[0], t[0], t[1]])
+            .collect(),
+        // 点和线没有面积, 光线打不到.
+        Mode::Points | Mode::Lines | Mode::LineLoop | Mode::LineStrip => return Ok(None),
+    };
+    if triangles.is_empty() {
+        return Ok(None);
+    }
+
+    // 法向量使用逆转置矩阵变换, 它和三个轴两两叉积组成的余子式矩阵只差一个行列式.
+    let [a, b, c] = axes(transform);
+    let det = a.dot(b.cross(c));
+    let normal_axes = [b.cross(c), c.cross(a), a.cross(b)].map(|n| n * det.signum());
+    if det < 0. {
+        // 镜像变换会让顶点顺序反过来.
+        triangles.iter_mut().for_each(|t| t.swap(1, 2));
+    }
+    let normals = reader.read_normals().map(|normals| {
+        normals
+            .map(|[x, y, z]| {
+                let [nx, ny, nz] = normal_axes;
+                z_up((nx * x + ny * y + nz * z).normalize())
+            })
+            .collect()
+    });
+    Ok(Some(MeshDesc {
+        vertices: positions
+            .into_iter()
+            .map(|p| z_up(transform_point(transform, p)))
+            .collect(),
+        triangles,
+        normals,
+        material: import_material(&primitive.material()),
+    }))
+}
+
+fn import_material(material: &gltf::Material) -> MaterialDesc {
+    let pbr = material.pbr_metallic_roughness();
+    let [r, g, b, _] = pbr.base_color_factor();
+    let base_color = Vec3::new(r, g, b);
+    let [er, eg, eb] = material.emissive_factor();
+    let emission = Vec3::new(er, eg, eb) * material.emissive_strength().unwrap_or(1.);
+    if !emission.is_zero() {
+        MaterialDesc::Emissive { emission }
+    } else if material
+        .transmission()
+        .is_some_and(|t| t.transmission_factor() > 0.)
+    {
+        MaterialDesc::Dielectric {
+            ior: material.ior().unwrap_or(1.5),
+            tint: base_color,
+        }
+    } else if pbr.metallic_factor() >= 0.5 {
+        MaterialDesc::Metal { albedo: base_color }
+    } else {
+        MaterialDesc::Diffuse { albedo: base_color }
+    }
+}
+
+/// 光照强度的换算和 Khronos 的参考查看器一致: 点光源和聚光灯的 candela, 平行光的 lux
+/// 直接作为渲染使用的亮度.
+fn import_light(light: &gltf::khr_lights_punctual::Light, transform: &Mat4) -> LightDesc {
+    let pos = z_up(transform_point(transform, [0., 0., 0.]));
+    // 光源沿节点的 -z 方向照射.
+    let direction = z_up(transform_vector(transform, [0., 0., -1.]));
+    let shape = match light.kind() {
+        Kind::Directional => LightShape::Directional { direction },
+        Kind::Point => LightShape::Point { pos },
+        Kind::Spot {
+            inner_cone_angle,
+            outer_cone_angle,
+        } => LightShape::Spot {
+            pos,
+            direction,
+            inner_angle: inner_cone_angle.to_degrees(),
+            outer_angle: outer_cone_angle.to_degrees(),
+        },
+    };
+    let [r, g, b] = light.color();
+    LightDesc {
+        shape,
+        strength: light.intensity(),
+        color: Vec3::new(r, g, b),
+    }
+}
+
+/// 相机沿节点的 -z 方向看, +y 是上方向.
+fn import_camera(camera: &gltf::Camera, transform: &Mat4) -> CameraDesc {
+    let mut desc = CameraDesc {
+        pos: z_up(transform_point(transform, [0., 0., 0.])),
+        gaze: z_up(transform_vector(transform, [0., 0., -1.])).normalize(),
+        up: z_up(transform_vector(transform, [0., 1., 0.])).normalize(),
+        ..CameraDesc::default()
+    };
+    match camera.projection() {
+        gltf::camera::Projection::Perspective(perspective) => {
+            desc.fov = perspective.yfov().to_degrees();
+        }
+        gltf::camera::Projection::Orthographic(orthographic) => {
+            desc.projection = Projection::Orthographic;
+            desc.ortho_height = orthographic.ymag() * 2.;
+        }
+    }
+    desc
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::Engine;
+    use serde_json::json;
+
+    use super::*;
+    use crate::ray_tracing::vector::approx_eq;
+
+    /// 内嵌缓冲区的 .gltf: 一个带法向量的三角形, 一个四边形 (分别作为条带和扇形),
+    /// 节点包括父子层级, 镜像缩放和一个点光源.
+    fn fixture() -> Vec<u8> {
+        let floats: [[f32; 3]; 10] = [
+            // 三角形顶点, 从 +z 看逆时针.
+            [0., 0., 0.],
+            [1., 0., 0.],
+            [0., 1., 0.],
+            // 三角形法向量.
+            [0., 0., 1.],
+            [0., 0., 1.],
+            [0., 0., 1.],
+            // 四边形顶点.
+            [0., 0., 0.],
+            [1., 0., 0.],
+            [0., 1., 0.],
+            [1., 1., 0.],
+        ];
+        let data = floats
+            .iter()
+            .flatten()
+            .flat_map(|f| f.to_le_bytes())
+            .collect::<Vec<_>>();
+        let accessor = |offset: usize, count: usize| {
+            json!({
+                "bufferView": 0, "byteOffset": offset, "componentType": 5126,
+                "count": count, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 1],
+            })
+        };
+        let primitive = |mode: u32, attributes| json!({ "primitives": [{ "attributes": attributes, "mode": mode }] });
+        let doc = json!({
+            "asset": { "version": "2.0" },
+            "scene": 0,
+            "scenes": [{ "nodes": [0, 2, 3, 4, 5] }],
+            "nodes": [
+                { "translation": [1, 0, 0], "scale": [2, 2, 2], "children": [1] },
+                { "translation": [0, 1, 0], "mesh": 0 },
+                { "scale": [-1, 1, 1], "mesh": 0 },
+                { "mesh": 1 },
+                { "mesh": 2 },
+                { "translation": [0, 3, 0], "extensions": { "KHR_lights_punctual": { "light": 0 } } },
+            ],
+            "meshes": [
+                primitive(4, json!({ "POSITION": 0, "NORMAL": 1 })),
+                primitive(5, json!({ "POSITION": 2 })),
+                primitive(6, json!({ "POSITION": 2 })),
+            ],
+            "accessors": [accessor(0, 3), accessor(36, 3), accessor(72, 4)],
+            "bufferViews": [{ "buffer": 0, "byteLength": data.len() }],
+            "buffers": [{
+                "byteLength": data.len(),
+                "uri": format!(
+                    "data:application/octet-stream;base64,{}",
+                    base64::engine::general_purpose::STANDARD.encode(&data)
+                ),
+            }],
+            "extensionsUsed": ["KHR_lights_punctual"],
+            "extensions": { "KHR_lights_punctual": { "lights": [{ "type": "point", "intensity": 10 }] } },
+        });
+        serde_json::to_vec(&doc).unwrap()
+    }
+
+    fn meshes(scene: &GltfScene) -> Vec<&MeshDesc> {
+        scene
+            .objects
+            .iter()
+            .map(|object| match object {
+                ObjectDesc::Mesh(mesh) => mesh,
+                _ => panic!("expected mesh, got {object:?}"),
+            })
+            .collect()
+    }
+
+    fn parse_fixture() -> GltfScene {
+        GltfScene::parse(&fixture(), |uri| Err(format!("unexpected uri {uri}"))).unwrap()
+    }
+
+    #[test]
+    fn composes_node_transforms() {
+        let scene = parse_fixture();
+        let child = meshes(&scene)[0];
+        // 世界变换 = 父节点 (平移 (1, 0, 0), 缩放 2) * 子节点 (平移 (0, 1, 0)), 再转换到 z 轴向上.
+        assert!(approx_eq(child.vertices[0], Vec3::new(1., 0., 2.)));
+        assert!(approx_eq(child.vertices[1], Vec3::new(3., 0., 2.)));
+        assert!(approx_eq(child.vertices[2], Vec3::new(1., 0., 4.)));
+        assert_eq!(child.triangles, vec![[0, 1, 2]]);
+    }
+
+    #[test]
+    fn mirrored_node_flips_winding() {
+        let scene = parse_fixture();
+        let mirrored = meshes(&scene)[1];
+        assert_eq!(mirrored.triangles, vec![[0, 2, 1]]);
+        let normal = mirrored.normals.as_ref().unwrap()[0];
+        assert!(approx_eq(normal, Vec3::new(0., -1., 0.)));
+        // 翻转之后的顶点顺序和法向量仍然在同一侧.
+        let [a, b, c] = mirrored.triangles[0].map(|i| mirrored.vertices[i]);
+        assert!((b - a).cross(c - a).dot(normal) > 0.);
+    }
+
+    #[test]
+    fn triangulates_strips_and_fans() {
+        let scene = parse_fixture();
+        let meshes = meshes(&scene);
+        assert_eq!(meshes[2].triangles, vec![[0, 1, 2], [2, 1, 3]]);
+        assert_eq!(meshes[3].triangles, vec![[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn imports_light_intensity_unchanged() {
+        let scene = parse_fixture();
+        assert_eq!(scene.lights.len(), 1);
+        assert_eq!(scene.lights[0].strength, 10.);
+        assert!(matches!(
+            scene.lights[0].shape,
+            LightShape::Point { pos } if approx_eq(pos, Vec3::new(0., 0., 3.))
+        ));
+    }
+
+    #[test]
+    fn rejects_external_buffers_without_loader() {
+        let doc = json!({
+            "asset": { "version": "2.0" },
+            "buffers": [{ "byteLength": 4, "uri": "data.bin" }],
+        });
+        let result = GltfScene::parse(&serde_json::to_vec(&doc).unwrap(), |uri| {
+            Err(format!("no {uri}"))
+        });
+        assert_eq!(result.unwrap_err(), "no data.bin");
+    }
+}
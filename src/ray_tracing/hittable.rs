@@ -0,0 +1,173 @@
+use std::any::Any;
+use std::fmt::Debug;
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::ray_tracing::material::Material;
+use crate::ray_tracing::vector::Vec3;
+use crate::ray_tracing::{Intersect, IntersectKind};
+
+/// 可以和光线求交的物体.
+///
+/// 场景中的所有物体 (球体, 平面以及用户自定义的物体) 都需要实现这个 trait,
+/// 然后通过 [`RayTracing::put_object`](super::RayTracing::put_object) 放入场景.
+///
+/// `&dyn Hittable` 可以转换成 `&dyn Any`, 再用 `downcast_ref` 取得具体的类型.
+pub trait Hittable: Any + Debug + Send + Sync {
+    /// 和从 `origin` 沿着 `direction` (标准化) 射出的光线求交,
+    /// 只返回距离在 `(0, max_distance)` 范围内的最近交点.
+    fn intersect(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<Intersect>;
+
+    /// 光线在 `(0, max_distance)` 范围内是否被这个物体挡住, 用于阴影检测.
+    ///
+    /// 默认用 [`intersect`](Hittable::intersect) 实现,
+    /// 由多个图元组成的物体可以重写它, 找到任意一个遮挡就提前返回.
+    fn occludes(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> bool {
+        self.intersect(origin, direction, max_distance).is_some()
+    }
+
+    /// 物体的轴对齐包围盒, 无限大的物体 (比如平面) 返回 None.
+    fn bounding_box(&self) -> Option<Aabb>;
+
+    /// 物体的类型, 默认是 [`IntersectKind::Other`].
+    fn kind(&self) -> IntersectKind {
+        IntersectKind::Other
+    }
+}
+
+/// 轴对齐包围盒 (Axis-Aligned Bounding Box).
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    #[must_use]
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// 同时包含两个包围盒的最小包围盒.
+    #[must_use]
+    pub fn union(self, rhs: Self) -> Self {
+        Self {
+            min: Vec3::new(
+                self.min.x.min(rhs.min.x),
+                self.min.y.min(rhs.min.y),
+                self.min.z.min(rhs.min.z),
+            ),
+            max: Vec3::new(
+                self.max.x.max(rhs.max.x),
+                self.max.y.max(rhs.max.y),
+                self.max.z.max(rhs.max.z),
+            ),
+        }
+    }
+
+    /// 包围盒中心.
+    #[must_use]
+    pub fn centroid(self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// 包围盒表面积.
+    #[must_use]
+    pub fn surface_area(self) -> f32 {
+        let d = self.max - self.min;
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    /// 光线是否在 `(0, max_distance)` 范围内穿过包围盒 (slab 方法).
+    ///
+    /// `inv_direction` 是光线方向各分量的倒数.
+    #[must_use]
+    pub fn hit(self, origin: Vec3, inv_direction: Vec3, max_distance: f32) -> bool {
+        let mut t_min = 0.0f32;
+        let mut t_max = max_distance;
+        for (o, inv, min, max) in [
+            (origin.x, inv_direction.x, self.min.x, self.max.x),
+            (origin.y, inv_direction.y, self.min.y, self.max.y),
+            (origin.z, inv_direction.z, self.min.z, self.max.z),
+        ] {
+            let t1 = (min - o) * inv;
+            let t2 = (max - o) * inv;
+            t_min = t_min.max(t1.min(t2));
+            t_max = t_max.min(t1.max(t2));
+        }
+        t_min <= t_max
+    }
+}
+
+/// 无限大的平面.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy)]
+pub struct Plane {
+    /// 平面上的一个点.
+    point: Vec3,
+    /// 平面法向量, 始终是标准化的.
+    normal: Vec3,
+    material: Material,
+}
+
+#[wasm_bindgen]
+impl Plane {
+    #[must_use]
+    pub fn new(point: Vec3, normal: Vec3, material: Material) -> Self {
+        Self {
+            point,
+            normal: normal.normalize(),
+            material,
+        }
+    }
+
+    #[must_use]
+    pub fn point(&self) -> Vec3 {
+        self.point
+    }
+
+    #[must_use]
+    pub fn normal(&self) -> Vec3 {
+        self.normal
+    }
+
+    #[must_use]
+    pub fn material(&self) -> Material {
+        self.material
+    }
+
+    pub fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+}
+
+impl Hittable for Plane {
+    fn intersect(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<Intersect> {
+        let denominator = self.normal.dot(direction);
+        if denominator.abs() < f32::EPSILON {
+            // 光线和平面平行.
+            return None;
+        }
+        let t = (self.point - origin).dot(self.normal) / denominator;
+        if t > 0.0 && t < max_distance {
+            Some(Intersect::new(
+                t,
+                origin + direction * t,
+                direction,
+                self.normal,
+                IntersectKind::Plane,
+                self.material,
+            ))
+        } else {
+            None
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+
+    fn kind(&self) -> IntersectKind {
+        IntersectKind::Plane
+    }
+}
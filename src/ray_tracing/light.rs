@@ -0,0 +1,353 @@
+use std::f32;
+
+use rand::Rng;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::ray_tracing::material::Material;
+use crate::ray_tracing::sampling;
+use crate::ray_tracing::vector::Vec3;
+use crate::ray_tracing::{Intersect, IntersectKind, Sphere};
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightKind {
+    /// 点光源, 产生硬阴影.
+    Point,
+    /// 平行光 (比如太阳), 没有位置, 也不随距离衰减.
+    Directional,
+    /// 聚光灯, 在内外锥角之间平滑衰减.
+    Spot,
+    /// 球形面光源.
+    Sphere,
+    /// 矩形面光源.
+    Rect,
+}
+
+/// 光源.
+///
+/// 除平行光外, 光照按距离的平方反比衰减, 多个光源的贡献直接相加.
+///
+/// 面光源在每条阴影光线上随机选取光源上的一个点, 多次采样平均之后得到软阴影,
+/// 同时面光源本身对相机光线可见.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct Light {
+    kind: LightKind,
+    /// 光源位置 (面光源的中心).
+    pos: Vec3,
+    /// 光照强度: 距离光源 1 米处正对光源时的亮度, 平行光则是任意位置的亮度.
+    strength: f32,
+    /// 光的颜色 (rgb, 0.0 ~ 1.0).
+    color: Vec3,
+    /// 平行光和聚光灯的照射方向, 始终是标准化的.
+    direction: Vec3,
+    /// 聚光灯内锥角和外锥角的余弦.
+    cos_inner: f32,
+    cos_outer: f32,
+    /// 球形面光源的半径.
+    radius: f32,
+    /// 矩形面光源的两条边, 从中心出发各延伸一半.
+    edge_u: Vec3,
+    edge_v: Vec3,
+}
+
+/// 对光源的一次采样.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LightSample {
+    /// 从被照射点指向光源的标准化方向.
+    pub(crate) direction: Vec3,
+    /// 到光源上采样点的距离, 平行光为无穷大.
+    pub(crate) distance: f32,
+    /// 正对光源时到达被照射点的光, 已经算上颜色和衰减.
+    pub(crate) radiance: Vec3,
+}
+
+#[wasm_bindgen]
+impl Light {
+    /// 点光源.
+    #[must_use]
+    pub fn new(pos: Vec3, strength: f32) -> Self {
+        Self {
+            kind: LightKind::Point,
+            pos,
+            strength,
+            color: Vec3::new(1., 1., 1.),
+            direction: -Vec3::Z,
+            cos_inner: 1.,
+            cos_outer: 1.,
+            radius: 0.,
+            edge_u: Vec3::ZERO,
+            edge_v: Vec3::ZERO,
+        }
+    }
+
+    /// 平行光, `direction` 是光的传播方向.
+    #[must_use]
+    pub fn directional(direction: Vec3, strength: f32) -> Self {
+        Self {
+            kind: LightKind::Directional,
+            direction: direction.normalize(),
+            ..Self::new(Vec3::ZERO, strength)
+        }
+    }
+
+    /// 聚光灯, `inner_angle` 和 `outer_angle` 是从中心轴算起的半角 (角度制),
+    /// 内锥角以内全亮, 外锥角以外没有光.
+    #[must_use]
+    pub fn spot(
+        pos: Vec3,
+        direction: Vec3,
+        inner_angle: f32,
+        outer_angle: f32,
+        strength: f32,
+    ) -> Self {
+        Self {
+            kind: LightKind::Spot,
+            direction: direction.normalize(),
+            cos_inner: inner_angle.to_radians().cos(),
+            cos_outer: outer_angle.max(inner_angle).to_radians().cos(),
+            ..Self::new(pos, strength)
+        }
+    }
+
+    /// 球形面光源.
+    #[must_use]
+    pub fn sphere(center: Vec3, radius: f32, strength: f32) -> Self {
+        Self {
+            kind: LightKind::Sphere,
+            radius,
+            ..Self::new(center, strength)
+        }
+    }
+
+    /// 矩形面光源, `edge_u` 和 `edge_v` 是矩形两条互相垂直的边.
+    /// 单面发光, 只朝 `edge_u × edge_v` 一侧照射, 从背面看不到也照不到.
+    #[must_use]
+    pub fn rect(center: Vec3, edge_u: Vec3, edge_v: Vec3, strength: f32) -> Self {
+        Self {
+            kind: LightKind::Rect,
+            edge_u,
+            edge_v,
+            ..Self::new(center, strength)
+        }
+    }
+
+    #[must_use]
+    pub fn kind(&self) -> LightKind {
+        self.kind
+    }
+
+    #[must_use]
+    pub fn pos(&self) -> Vec3 {
+        self.pos
+    }
+
+    /// 设置光源位置 (面光源的中心), 平行光没有位置.
+    pub fn set_pos(&mut self, pos: Vec3) {
+        self.pos = pos;
+    }
+
+    #[must_use]
+    pub fn strength(&self) -> f32 {
+        self.strength
+    }
+
+    pub fn set_strength(&mut self, strength: f32) {
+        self.strength = strength;
+    }
+
+    #[must_use]
+    pub fn color(&self) -> Vec3 {
+        self.color
+    }
+
+    /// 设置光的颜色 (rgb, 0.0 ~ 1.0).
+    pub fn set_color(&mut self, color: Vec3) {
+        self.color = color;
+    }
+
+    /// 平行光和聚光灯的照射方向 (标准化).
+    #[must_use]
+    pub fn direction(&self) -> Vec3 {
+        self.direction
+    }
+
+    /// 聚光灯的内锥半角 (角度制).
+    #[must_use]
+    pub fn inner_angle(&self) -> f32 {
+        self.cos_inner.clamp(-1., 1.).acos().to_degrees()
+    }
+
+    /// 聚光灯的外锥半角 (角度制).
+    #[must_use]
+    pub fn outer_angle(&self) -> f32 {
+        self.cos_outer.clamp(-1., 1.).acos().to_degrees()
+    }
+
+    /// 球形面光源的半径.
+    #[must_use]
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    #[must_use]
+    pub fn edge_u(&self) -> Vec3 {
+        self.edge_u
+    }
+
+    #[must_use]
+    pub fn edge_v(&self) -> Vec3 {
+        self.edge_v
+    }
+}
+
+impl Light {
+    /// 面光源的面积, 球形光源取它的截面积.
+    fn area(&self) -> f32 {
+        match self.kind {
+            LightKind::Sphere => f32::consts::PI * self.radius * self.radius,
+            LightKind::Rect => self.edge_u.cross(self.edge_v).magnitude(),
+            LightKind::Point | LightKind::Directional | LightKind::Spot => 0.,
+        }
+    }
+
+    /// 矩形光源的发光方向.
+    fn rect_normal(&self) -> Vec3 {
+        self.edge_u.cross(self.edge_v).normalize()
+    }
+
+    /// 面光源被看到时的颜色, 光照强度平摊到整个面上.
+    fn emission(&self) -> Vec3 {
+        self.color * (self.strength / self.area().max(f32::EPSILON))
+    }
+
+    /// 从 `point` 看向光源, 面光源会随机选取光源上的一个点.
+    pub(crate) fn sample(&self, point: Vec3, rng: &mut impl Rng) -> LightSample {
+        let target = match self.kind {
+            LightKind::Directional => {
+                return LightSample {
+                    direction: -self.direction,
+                    distance: f32::INFINITY,
+                    radiance: self.color * self.strength,
+                };
+            }
+            LightKind::Point | LightKind::Spot => self.pos,
+            LightKind::Sphere => {
+                // 在朝向 point 的圆盘上采样, 圆盘就是从 point 看到的球体轮廓.
+                let (tangent, bitangent) =
+                    sampling::orthonormal_basis((self.pos - point).normalize());
+                let (x, y) = sampling::unit_disk(rng);
+                self.pos + (tangent * x + bitangent * y) * self.radius
+            }
+            LightKind::Rect => {
+                self.pos
+                    + self.edge_u * (rng.random::<f32>() - 0.5)
+                    + self.edge_v * (rng.random::<f32>() - 0.5)
+            }
+        };
+        let to_light = target - point;
+        let distance = to_light.magnitude();
+        let direction = to_light / distance;
+        let mut strength = self.strength / (distance * distance).max(f32::EPSILON);
+        if self.kind == LightKind::Rect {
+            // 面光源的发光余弦, 背面为 0.
+            strength *= (-direction).dot(self.rect_normal()).max(0.);
+        }
+        if self.kind == LightKind::Spot {
+            let cos_theta = (-direction).dot(self.direction);
+            let t = ((cos_theta - self.cos_outer) / (self.cos_inner - self.cos_outer).max(1e-4))
+                .clamp(0.0, 1.0);
+            // smoothstep
+            strength *= t * t * (3.0 - 2.0 * t);
+        }
+        LightSample {
+            direction,
+            distance,
+            radiance: self.color * strength,
+        }
+    }
+
+    /// 面光源和光线求交, 其他光源不会被打中.
+    pub(crate) fn intersect(&self, origin: Vec3, direction: Vec3) -> Option<Intersect> {
+        let material = Material::emissive(self.emission());
+        let mut intersect = match self.kind {
+            LightKind::Point | LightKind::Directional | LightKind::Spot => return None,
+            LightKind::Sphere => Sphere::with_material(self.pos, self.radius, material)
+                .intersect(origin, direction)?,
+            LightKind::Rect => {
+                let normal = self.rect_normal();
+                let denominator = normal.dot(direction);
+                // 单面发光, 从背面或者平行方向射来的光线打不中.
+                if denominator > -f32::EPSILON {
+                    return None;
+                }
+                let t = (self.pos - origin).dot(normal) / denominator;
+                if t <= 0.0 {
+                    return None;
+                }
+                let hit_point = origin + direction * t;
+                // 投影到两条边上, 判断是否在矩形内.
+                let offset = hit_point - self.pos;
+                let u = offset.dot(self.edge_u) / self.edge_u.dot(self.edge_u);
+                let v = offset.dot(self.edge_v) / self.edge_v.dot(self.edge_v);
+                if u.abs() > 0.5 || v.abs() > 0.5 {
+                    return None;
+                }
+                Intersect::new(
+                    t,
+                    hit_point,
+                    direction,
+                    normal,
+                    IntersectKind::Light,
+                    material,
+                )
+            }
+        };
+        intersect.kind = IntersectKind::Light;
+        Some(intersect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::SmallRng;
+
+    use super::*;
+
+    /// 朝 -z 发光的 2x2 矩形光源, 位于 z = 1.
+    fn rect() -> Light {
+        Light::rect(
+            Vec3::new(0., 0., 1.),
+            Vec3::new(0., 2., 0.),
+            Vec3::new(2., 0., 0.),
+            1.,
+        )
+    }
+
+    #[test]
+    fn rect_light_is_one_sided() {
+        let light = rect();
+        let mut rng = SmallRng::seed_from_u64(0);
+        for _ in 0..16 {
+            assert!(light.sample(Vec3::new(0., 0., 0.), &mut rng).radiance.x > 0.);
+            assert_eq!(light.sample(Vec3::new(0., 0., 2.), &mut rng).radiance.x, 0.);
+        }
+        assert!(light.intersect(Vec3::ZERO, Vec3::Z).is_some());
+        assert!(light.intersect(Vec3::new(0., 0., 2.), -Vec3::Z).is_none());
+    }
+
+    #[test]
+    fn rect_light_falls_off_with_emitter_cosine() {
+        let light = Light::rect(
+            Vec3::ZERO,
+            Vec3::new(0., 0.01, 0.),
+            Vec3::new(0.01, 0., 0.),
+            1.,
+        );
+        let mut rng = SmallRng::seed_from_u64(0);
+        let head_on = light.sample(Vec3::new(0., 0., -1.), &mut rng).radiance.x;
+        let grazing = light.sample(Vec3::new(0.6, 0., -0.8), &mut rng).radiance.x;
+        assert!((grazing / head_on - 0.8).abs() < 0.01);
+    }
+}
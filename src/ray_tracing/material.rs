@@ -0,0 +1,196 @@
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::ray_tracing::vector::Vec3;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaterialKind {
+    /// 漫反射 (兰伯特).
+    Diffuse,
+    /// 金属/镜面, 反射光线会乘上 albedo.
+    Metal,
+    /// 电介质 (玻璃, 水), 会同时折射和反射.
+    Dielectric,
+    /// 自发光, 不受光照影响.
+    Emissive,
+}
+
+/// 棋盘格纹理.
+#[derive(Debug, Clone, Copy)]
+struct Checker {
+    /// 另一种格子的颜色, 第一种是 albedo.
+    color: Vec3,
+    /// 格子大小 (米).
+    grid_size: f32,
+}
+
+/// 物体表面材质, 决定光线打到物体上之后怎么着色.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy)]
+pub struct Material {
+    kind: MaterialKind,
+    /// 反照率 (颜色, 0.0 ~ 1.0).
+    albedo: Vec3,
+    /// 自发光颜色.
+    emission: Vec3,
+    /// 折射率 (index of refraction), 只对电介质有效.
+    ior: f32,
+    checker: Option<Checker>,
+}
+
+/// 默认材质是反射 60% 光线的灰色金属, 也是 [`Sphere::new`](super::Sphere::new) 使用的材质.
+impl Default for Material {
+    fn default() -> Self {
+        Self::metal(Vec3::new(0.6, 0.6, 0.6))
+    }
+}
+
+#[wasm_bindgen]
+impl Material {
+    /// 漫反射材质.
+    #[must_use]
+    pub fn diffuse(albedo: Vec3) -> Self {
+        Self {
+            kind: MaterialKind::Diffuse,
+            albedo,
+            emission: Vec3::ZERO,
+            ior: 1.0,
+            checker: None,
+        }
+    }
+
+    /// 棋盘格漫反射材质, 两种颜色交替出现.
+    #[must_use]
+    pub fn checker(color_1: Vec3, color_2: Vec3, grid_size: f32) -> Self {
+        Self {
+            checker: Some(Checker {
+                color: color_2,
+                grid_size,
+            }),
+            ..Self::diffuse(color_1)
+        }
+    }
+
+    /// 金属材质, albedo 为 1 时是完美的镜子.
+    #[must_use]
+    pub fn metal(albedo: Vec3) -> Self {
+        Self {
+            kind: MaterialKind::Metal,
+            albedo,
+            emission: Vec3::ZERO,
+            ior: 1.0,
+            checker: None,
+        }
+    }
+
+    /// 透明的电介质材质, 例如玻璃 (ior 约 1.5), 水 (ior 约 1.33).
+    #[must_use]
+    pub fn dielectric(ior: f32) -> Self {
+        Self::tinted_dielectric(ior, Vec3::new(1., 1., 1.))
+    }
+
+    /// 带颜色的电介质材质, 折射光会乘上 tint.
+    #[must_use]
+    pub fn tinted_dielectric(ior: f32, tint: Vec3) -> Self {
+        Self {
+            kind: MaterialKind::Dielectric,
+            albedo: tint,
+            emission: Vec3::ZERO,
+            ior,
+            checker: None,
+        }
+    }
+
+    /// 默认地面的材质: 0.3 米的红白棋盘格.
+    #[must_use]
+    pub fn default_ground() -> Self {
+        Self::checker(Vec3::new(0.9, 0.1, 0.1), Vec3::new(0.9, 0.9, 0.9), 0.3)
+    }
+
+    /// 自发光材质.
+    #[must_use]
+    pub fn emissive(emission: Vec3) -> Self {
+        Self {
+            kind: MaterialKind::Emissive,
+            albedo: Vec3::ZERO,
+            emission,
+            ior: 1.0,
+            checker: None,
+        }
+    }
+
+    #[must_use]
+    pub fn kind(&self) -> MaterialKind {
+        self.kind
+    }
+
+    #[must_use]
+    pub fn albedo(&self) -> Vec3 {
+        self.albedo
+    }
+
+    #[must_use]
+    pub fn emission(&self) -> Vec3 {
+        self.emission
+    }
+
+    #[must_use]
+    pub fn ior(&self) -> f32 {
+        self.ior
+    }
+}
+
+impl Material {
+    /// 棋盘格纹理的另一种颜色和格子大小, 不是棋盘格时为 None.
+    #[must_use]
+    pub fn checker_pattern(&self) -> Option<(Vec3, f32)> {
+        self.checker.map(|c| (c.color, c.grid_size))
+    }
+
+    /// 获取某个点上的反照率, 考虑纹理.
+    #[must_use]
+    pub fn albedo_at(&self, point: Vec3) -> Vec3 {
+        match self.checker {
+            Some(Checker { color, grid_size }) => {
+                // 根据格子坐标选择颜色.
+                let Vec3 { x, y, z: _ } = point;
+                if ((x / grid_size).floor() as i32 + (y / grid_size).floor() as i32) % 2 == 0 {
+                    self.albedo
+                } else {
+                    color
+                }
+            }
+            None => self.albedo,
+        }
+    }
+
+    /// 计算光线在电介质表面的折射方向 (标准化) 和菲涅尔反射率.
+    ///
+    /// `normal` 朝向光线射来的一侧, `front_face` 表示光线是否从外部射入.
+    /// 发生全反射时返回 None.
+    #[must_use]
+    pub fn refract(&self, direction: Vec3, normal: Vec3, front_face: bool) -> Option<(Vec3, f32)> {
+        // 折射率之比 (入射介质 / 出射介质).
+        let eta = if front_face { 1.0 / self.ior } else { self.ior };
+        let cos_i = normal.dot(-direction).min(1.0);
+        let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+        if sin2_t > 1.0 {
+            return None;
+        }
+        let cos_t = (1.0 - sin2_t).sqrt();
+        // Snell 定律求折射方向.
+        let refract_direction = direction * eta + normal * (eta * cos_i - cos_t);
+        // 菲涅尔项要用光疏介质一侧的角度.
+        let fresnel = Self::schlick(if front_face { cos_i } else { cos_t }, self.ior);
+        Some((refract_direction.normalize(), fresnel))
+    }
+
+    /// 使用 Schlick 近似计算菲涅尔反射率.
+    ///
+    /// `cos_theta` 是光线在光疏介质一侧和法线的夹角余弦.
+    #[must_use]
+    pub fn schlick(cos_theta: f32, ior: f32) -> f32 {
+        let r0 = ((1.0 - ior) / (1.0 + ior)).powi(2);
+        r0 + (1.0 - r0) * (1.0 - cos_theta).powi(5)
+    }
+}
@@ -0,0 +1,482 @@
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::ray_tracing::bvh::Bvh;
+use crate::ray_tracing::hittable::{Aabb, Hittable};
+use crate::ray_tracing::material::Material;
+use crate::ray_tracing::vector::Vec3;
+use crate::ray_tracing::{Intersect, IntersectKind};
+
+/// 三角形, 顶点按逆时针顺序排列时几何法向量朝外.
+#[derive(Debug, Clone, Copy)]
+pub struct Triangle {
+    vertices: [Vec3; 3],
+    /// 顶点法向量, 用于平滑着色.
+    normals: Option<[Vec3; 3]>,
+    /// 顶点纹理坐标.
+    uvs: Option<[(f32, f32); 3]>,
+}
+
+impl Triangle {
+    #[must_use]
+    pub fn new(vertices: [Vec3; 3]) -> Self {
+        Self {
+            vertices,
+            normals: None,
+            uvs: None,
+        }
+    }
+
+    /// 带顶点法向量的三角形, 有长度为 0 的法向量时退化为使用几何法向量.
+    #[must_use]
+    pub fn with_normals(vertices: [Vec3; 3], normals: [Vec3; 3]) -> Self {
+        let valid = normals.iter().all(|n| n.magnitude() > f32::EPSILON);
+        Self {
+            normals: valid.then(|| normals.map(Vec3::normalize)),
+            ..Self::new(vertices)
+        }
+    }
+
+    #[must_use]
+    pub fn vertices(&self) -> [Vec3; 3] {
+        self.vertices
+    }
+
+    #[must_use]
+    pub fn normals(&self) -> Option<[Vec3; 3]> {
+        self.normals
+    }
+
+    #[must_use]
+    pub fn uvs(&self) -> Option<[(f32, f32); 3]> {
+        self.uvs
+    }
+
+    #[must_use]
+    pub fn bounding_box(&self) -> Aabb {
+        let [a, b, c] = self.vertices;
+        Aabb::new(a, a)
+            .union(Aabb::new(b, b))
+            .union(Aabb::new(c, c))
+    }
+
+    /// Möller–Trumbore 求交, 返回 (距离, 重心坐标 u, 重心坐标 v).
+    #[must_use]
+    pub fn intersect(&self, origin: Vec3, direction: Vec3) -> Option<(f32, f32, f32)> {
+        let [a, b, c] = self.vertices;
+        let edge_1 = b - a;
+        let edge_2 = c - a;
+        let p = direction.cross(edge_2);
+        let det = edge_1.dot(p);
+        if det.abs() < f32::EPSILON {
+            // 光线和三角形平行.
+            return None;
+        }
+        let inv_det = 1.0 / det;
+        let s = origin - a;
+        let u = s.dot(p) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(edge_1);
+        let v = direction.dot(q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = edge_2.dot(q) * inv_det;
+        (t > 0.0).then_some((t, u, v))
+    }
+
+    /// 由顶点顺序决定的几何法向量.
+    #[must_use]
+    pub fn geometric_normal(&self) -> Vec3 {
+        let [a, b, c] = self.vertices;
+        (b - a).cross(c - a).normalize()
+    }
+
+    /// 某个重心坐标处的法向量, 有顶点法向量时进行插值, 只用于着色.
+    #[must_use]
+    pub fn normal_at(&self, u: f32, v: f32) -> Vec3 {
+        match self.normals {
+            Some([n0, n1, n2]) => {
+                let n = n0 * (1.0 - u - v) + n1 * u + n2 * v;
+                if n.magnitude() > f32::EPSILON {
+                    n.normalize()
+                } else {
+                    self.geometric_normal()
+                }
+            }
+            None => self.geometric_normal(),
+        }
+    }
+
+    fn map_points(&mut self, f: impl Fn(Vec3) -> Vec3) {
+        self.vertices = self.vertices.map(&f);
+    }
+
+    fn map_normals(&mut self, f: impl Fn(Vec3) -> Vec3) {
+        self.normals = self.normals.map(|n| n.map(&f));
+    }
+}
+
+/// 三角形网格.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    triangles: Vec<Triangle>,
+    material: Material,
+    /// 三角形的 BVH, 每次变换之后重建.
+    bvh: Bvh,
+}
+
+impl Mesh {
+    #[must_use]
+    pub fn new(triangles: Vec<Triangle>, material: Material) -> Self {
+        let mut self_ = Self {
+            triangles,
+            material,
+            bvh: Bvh::default(),
+        };
+        self_.update_bvh();
+        self_
+    }
+
+    #[must_use]
+    pub fn triangles(&self) -> &[Triangle] {
+        &self.triangles
+    }
+
+    fn update_bvh(&mut self) {
+        self.bvh = Bvh::build(
+            self.triangles
+                .iter()
+                .map(Triangle::bounding_box)
+                .enumerate()
+                .collect(),
+        );
+    }
+
+    /// 解析 Wavefront OBJ 文件内容.
+    ///
+    /// 支持 `v`, `vn`, `vt`, `f` 语句, 多边形面会被拆分成三角形 (扇形),
+    /// 其他语句 (`o`, `g`, `s`, `usemtl` 等) 会被忽略.
+    pub fn parse_obj(src: &str, material: Material) -> Result<Self, String> {
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        let mut triangles = Vec::new();
+
+        for (line_number, line) in src.lines().enumerate() {
+            let line_number = line_number + 1;
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let mut tokens = line.split_whitespace();
+            let Some(keyword) = tokens.next() else {
+                continue;
+            };
+            let mut floats = || -> Result<Vec<f32>, String> {
+                tokens
+                    .by_ref()
+                    .map(|t| {
+                        t.parse::<f32>()
+                            .map_err(|e| format!("line {line_number}: invalid number {t:?}: {e}"))
+                    })
+                    .collect()
+            };
+            match keyword {
+                "v" | "vn" => {
+                    let values = floats()?;
+                    let [x, y, z, ..] = values[..] else {
+                        return Err(format!("line {line_number}: expected 3 coordinates"));
+                    };
+                    if keyword == "v" {
+                        positions.push(Vec3::new(x, y, z));
+                    } else {
+                        // 先不标准化, 长度为 0 的法向量由 Triangle::with_normals 处理.
+                        normals.push(Vec3::new(x, y, z));
+                    }
+                }
+                "vt" => {
+                    let values = floats()?;
+                    let Some(&u) = values.first() else {
+                        return Err(format!("line {line_number}: expected texture coordinate"));
+                    };
+                    uvs.push((u, values.get(1).copied().unwrap_or(0.0)));
+                }
+                "f" => {
+                    let corners = tokens
+                        .map(|t| {
+                            parse_face_corner(t, positions.len(), uvs.len(), normals.len())
+                                .map_err(|e| format!("line {line_number}: {e}"))
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if corners.len() < 3 {
+                        return Err(format!(
+                            "line {line_number}: face needs at least 3 vertices"
+                        ));
+                    }
+                    for i in 1..corners.len() - 1 {
+                        let face = [corners[0], corners[i], corners[i + 1]];
+                        let vertices = face.map(|(v, _, _)| positions[v]);
+                        let mut triangle = match face.map(|(_, _, n)| n) {
+                            [Some(a), Some(b), Some(c)] => Triangle::with_normals(
+                                vertices,
+                                [normals[a], normals[b], normals[c]],
+                            ),
+                            _ => Triangle::new(vertices),
+                        };
+                        if let [Some(a), Some(b), Some(c)] = face.map(|(_, t, _)| t) {
+                            triangle.uvs = Some([uvs[a], uvs[b], uvs[c]]);
+                        }
+                        triangles.push(triangle);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if triangles.is_empty() {
+            return Err("obj contains no faces".to_string());
+        }
+        Ok(Self::new(triangles, material))
+    }
+}
+
+/// 解析面的一个顶点 `v`, `v/vt`, `v//vn` 或 `v/vt/vn`, 返回从 0 开始的下标.
+fn parse_face_corner(
+    token: &str,
+    positions: usize,
+    uvs: usize,
+    normals: usize,
+) -> Result<(usize, Option<usize>, Option<usize>), String> {
+    // obj 下标从 1 开始, 负数表示从末尾倒数.
+    let resolve = |index: &str, len: usize| -> Result<usize, String> {
+        let i = index
+            .parse::<isize>()
+            .map_err(|e| format!("invalid index {index:?}: {e}"))?;
+        let resolved = if i < 0 { len as isize + i } else { i - 1 };
+        if (0..len as isize).contains(&resolved) {
+            Ok(resolved as usize)
+        } else {
+            Err(format!("index {i} out of range"))
+        }
+    };
+    let mut parts = token.split('/');
+    let v = resolve(parts.next().unwrap_or_default(), positions)?;
+    let vt = match parts.next() {
+        Some(t) if !t.is_empty() => Some(resolve(t, uvs)?),
+        _ => None,
+    };
+    let vn = match parts.next() {
+        Some(n) if !n.is_empty() => Some(resolve(n, normals)?),
+        _ => None,
+    };
+    Ok((v, vt, vn))
+}
+
+#[wasm_bindgen]
+impl Mesh {
+    /// 从 OBJ 文件的字节解析网格, 可以直接传入 js 中 fetch 得到的数据.
+    pub fn from_obj(bytes: &[u8], material: Material) -> Result<Mesh, String> {
+        let src = std::str::from_utf8(bytes).map_err(|e| format!("obj is not utf-8: {e}"))?;
+        Self::parse_obj(src, material)
+    }
+
+    #[must_use]
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+
+    #[must_use]
+    pub fn material(&self) -> Material {
+        self.material
+    }
+
+    pub fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    /// 以原点为中心缩放.
+    pub fn scale(&mut self, factor: f32) {
+        self.transform_points(|p| p * factor);
+    }
+
+    pub fn translate(&mut self, offset: Vec3) {
+        self.transform_points(|p| p + offset);
+    }
+
+    /// 把 y 轴向上的模型 (大部分建模软件导出的 OBJ) 转换到本项目 z 轴向上的坐标系.
+    pub fn y_up_to_z_up(&mut self) {
+        let f = |p: Vec3| Vec3::new(p.x, -p.z, p.y);
+        self.triangles.iter_mut().for_each(|t| {
+            t.map_points(f);
+            t.map_normals(f);
+        });
+        self.update_bvh();
+    }
+
+    /// 把网格缩放平移到底面中心在 `base`, 最大边长为 `size` 的位置.
+    /// 网格退化成一个点时只做平移.
+    pub fn fit_to(&mut self, base: Vec3, size: f32) {
+        let Some(bb) = self.bvh.bounds() else {
+            return;
+        };
+        let extent = bb.max - bb.min;
+        let longest = extent.x.max(extent.y).max(extent.z);
+        let bottom_center = Vec3::new(bb.centroid().x, bb.centroid().y, bb.min.z);
+        let factor = if longest > f32::EPSILON {
+            size / longest
+        } else {
+            1.
+        };
+        // 合并成一次变换, 只重建一次 BVH.
+        self.transform_points(|p| (p - bottom_center) * factor + base);
+    }
+}
+
+impl Mesh {
+    /// 变换所有顶点, 然后重建 BVH.
+    fn transform_points(&mut self, f: impl Fn(Vec3) -> Vec3) {
+        self.triangles.iter_mut().for_each(|t| t.map_points(&f));
+        self.update_bvh();
+    }
+}
+
+impl Hittable for Mesh {
+    fn intersect(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<Intersect> {
+        let mut closest: Option<(f32, f32, f32, &Triangle)> = None;
+        self.bvh
+            .closest_hit(origin, direction, max_distance, |i, max_distance| {
+                let triangle = &self.triangles[i];
+                let (t, u, v) = triangle.intersect(origin, direction)?;
+                (t < max_distance).then(|| {
+                    closest = Some((t, u, v, triangle));
+                    t
+                })
+            });
+        let (t, u, v, triangle) = closest?;
+        // 内外由几何法向量决定, 插值的法向量只用于着色.
+        Some(Intersect::with_shading_normal(
+            t,
+            origin + direction * t,
+            direction,
+            triangle.geometric_normal(),
+            triangle.normal_at(u, v),
+            IntersectKind::Mesh,
+            self.material,
+        ))
+    }
+
+    fn occludes(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> bool {
+        self.bvh.any_hit(origin, direction, max_distance, |i| {
+            self.triangles[i]
+                .intersect(origin, direction)
+                .is_some_and(|(t, _, _)| t < max_distance)
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bvh.bounds()
+    }
+
+    fn kind(&self) -> IntersectKind {
+        IntersectKind::Mesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray_tracing::vector::approx_eq;
+
+    fn parse(src: &str) -> Result<Mesh, String> {
+        Mesh::parse_obj(src, Material::diffuse(Vec3::new(1., 1., 1.)))
+    }
+
+    const SQUARE: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n";
+
+    #[test]
+    fn splits_polygons_into_fans() {
+        let mesh = parse(&format!("{SQUARE}v 0.5 2 0\nf 1 2 3 4 5\n")).unwrap();
+        assert_eq!(mesh.triangle_count(), 3);
+        let first = mesh.triangles()[0].vertices();
+        let last = mesh.triangles()[2].vertices();
+        assert!(approx_eq(first[0], Vec3::ZERO) && approx_eq(last[0], Vec3::ZERO));
+        assert!(approx_eq(last[2], Vec3::new(0.5, 2., 0.)));
+    }
+
+    #[test]
+    fn resolves_negative_indices() {
+        let mesh = parse(&format!("{SQUARE}f -4 -3 -2\n")).unwrap();
+        let [a, b, c] = mesh.triangles()[0].vertices();
+        assert!(
+            approx_eq(a, Vec3::ZERO)
+                && approx_eq(b, Vec3::X)
+                && approx_eq(c, Vec3::new(1., 1., 0.))
+        );
+    }
+
+    #[test]
+    fn parses_normal_and_uv_corners() {
+        let mesh = parse(&format!("{SQUARE}vn 0 0 2\nf 1//1 2//1 3//1\n")).unwrap();
+        let triangle = mesh.triangles()[0];
+        assert!(approx_eq(triangle.normals().unwrap()[0], Vec3::Z));
+        assert!(triangle.uvs().is_none());
+
+        let mesh = parse(&format!("{SQUARE}vt 0 0\nvt 1 0\nvt 1 1\nf 1/1 2/2 3/3\n")).unwrap();
+        let triangle = mesh.triangles()[0];
+        assert_eq!(triangle.uvs(), Some([(0., 0.), (1., 0.), (1., 1.)]));
+        assert!(triangle.normals().is_none());
+    }
+
+    #[test]
+    fn zero_normal_falls_back_to_geometric() {
+        let mesh = parse(&format!("{SQUARE}vn 0 0 0\nf 1//1 2//1 3//1\n")).unwrap();
+        let triangle = mesh.triangles()[0];
+        assert!(triangle.normals().is_none());
+        assert!(approx_eq(triangle.normal_at(0.2, 0.2), Vec3::Z));
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let error = parse(&format!("{SQUARE}f 1 2 5\n")).unwrap_err();
+        assert!(
+            error.contains("line 5") && error.contains("out of range"),
+            "{error}"
+        );
+        assert!(parse(&format!("{SQUARE}f 1 2 -5\n")).is_err());
+        assert!(parse(&format!("{SQUARE}f 1//2 2//2 3//2\n")).is_err());
+        assert!(parse(&format!("{SQUARE}f 1 2\n")).is_err());
+        assert_eq!(parse(SQUARE).unwrap_err(), "obj contains no faces");
+    }
+
+    #[test]
+    fn front_face_uses_geometric_normal() {
+        // 几何法向量朝 +z, 顶点法向量严重倾斜.
+        let tilted = Vec3::new(1., 0., 0.3);
+        let triangle = Triangle::with_normals(
+            [
+                Vec3::new(-1., -1., 0.),
+                Vec3::new(1., -1., 0.),
+                Vec3::new(0., 1., 0.),
+            ],
+            [tilted; 3],
+        );
+        let mesh = Mesh::new(vec![triangle], Material::diffuse(Vec3::new(1., 1., 1.)));
+        let direction = Vec3::new(0.8, 0., -0.6);
+        let intersect = mesh
+            .intersect(Vec3::new(0., 0., 0.) - direction, direction, f32::INFINITY)
+            .unwrap();
+        assert!(intersect.front_face());
+        assert!(approx_eq(intersect.normal().unwrap(), tilted.normalize()));
+    }
+
+    #[test]
+    fn fit_to_handles_degenerate_mesh() {
+        let point = Triangle::new([Vec3::new(1., 2., 3.); 3]);
+        let mut mesh = Mesh::new(vec![point], Material::diffuse(Vec3::new(1., 1., 1.)));
+        mesh.fit_to(Vec3::new(0., 0., 1.), 2.);
+        assert!(approx_eq(
+            mesh.triangles()[0].vertices()[0],
+            Vec3::new(0., 0., 1.)
+        ));
+    }
+}
@@ -1,18 +1,47 @@
 use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
 #[cfg(feature = "rayon")]
-use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::any::Any;
 use std::f32;
-use std::ops::{Div, Rem};
+use std::ops::{Add, Rem};
 use std::panic;
+use std::path::Path;
 use std::time::Duration;
 use wasm_bindgen::prelude::wasm_bindgen;
 
-use crate::ray_tracing::action::ActionManager;
+use crate::ray_tracing::action::{ActionManager, Axis};
+use crate::ray_tracing::bvh::Bvh;
+use crate::ray_tracing::camera::{Camera, Projection, ViewPlane};
+use crate::ray_tracing::environment::EnvironmentMap;
+use crate::ray_tracing::export::ImageFormat;
+use crate::ray_tracing::gltf_import::GltfScene;
+use crate::ray_tracing::hittable::{Aabb, Hittable, Plane};
+pub use crate::ray_tracing::light::Light;
+use crate::ray_tracing::material::{Material, MaterialKind};
+use crate::ray_tracing::mesh::Mesh;
+use crate::ray_tracing::scene::{ObjectDesc, Scene};
+use crate::ray_tracing::settings::{Integrator, RenderSettings};
+use crate::ray_tracing::sky::PhysicalSky;
 use crate::ray_tracing::vector::Vec3;
 use crate::time::Instant;
 
 pub mod action;
+pub mod bvh;
+pub mod camera;
+pub mod environment;
+pub mod export;
+pub mod gltf_import;
+pub mod hittable;
+pub mod light;
+pub mod material;
+pub mod mesh;
+mod path_tracing;
+pub mod sampling;
+pub mod scene;
+pub mod settings;
+pub mod sky;
+pub mod tone_mapping;
 pub mod vector;
 
 #[wasm_bindgen(start)]
@@ -26,10 +55,23 @@ pub const fn rgb(r: u8, g: u8, b: u8) -> u32 {
     0xFF000000 | (((r as u32) << 16) + ((g as u32) << 8) + b as u32)
 }
 
+/// 0.0 ~ 1.0 的颜色分量转 0xAARRGGBB, 超出范围的值会被截断.
 #[inline]
 #[must_use]
 pub const fn rgbf(r: f32, g: f32, b: f32) -> u32 {
-    rgb((r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8)
+    rgb(
+        (r.clamp(0.0, 1.0) * 255.0 + 0.5) as u8,
+        (g.clamp(0.0, 1.0) * 255.0 + 0.5) as u8,
+        (b.clamp(0.0, 1.0) * 255.0 + 0.5) as u8,
+    )
+}
+
+/// 把种子和一个下标混合成新的种子 (splitmix64), 相邻的下标得到互不相关的种子.
+const fn mix_seed(seed: u64, index: u64) -> u64 {
+    let mut z = seed ^ index.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
 }
 
 /// 输入为 0xAARRGGBB, 输出为 [0xRR, 0xGG, 0xBB, 0xAA]
@@ -51,6 +93,13 @@ pub enum IntersectKind {
     Sky,
     Ground,
     Sphere,
+    Plane,
+    /// 三角形网格.
+    Mesh,
+    /// 面光源.
+    Light,
+    /// 用户自定义的物体.
+    Other,
 }
 
 #[wasm_bindgen]
@@ -58,24 +107,206 @@ pub enum IntersectKind {
 pub struct Intersect {
     distance: f32,
     hit_point: Option<Vec3>,
+    /// 法向量, 始终朝向光线射来的一侧.
     normal: Option<Vec3>,
+    /// 光线是否从物体外部射入.
+    front_face: bool,
     kind: IntersectKind,
+    /// 相交处的材质, 天空没有材质.
+    material: Option<Material>,
+}
+
+impl Intersect {
+    /// 构造一个相交结果.
+    ///
+    /// `outward_normal` 是物体表面朝外的标准化法向量,
+    /// 会根据光线方向 `direction` 翻转成朝向光线射来的一侧.
+    #[must_use]
+    pub fn new(
+        distance: f32,
+        hit_point: Vec3,
+        direction: Vec3,
+        outward_normal: Vec3,
+        kind: IntersectKind,
+        material: Material,
+    ) -> Self {
+        Self::with_shading_normal(
+            distance,
+            hit_point,
+            direction,
+            outward_normal,
+            outward_normal,
+            kind,
+            material,
+        )
+    }
+
+    /// 和 [`new`](Self::new) 相同, 但是着色用的法向量 `shading_normal` (比如插值得到的顶点法向量)
+    /// 和判断内外的几何法向量 `outward_normal` 分开.
+    #[must_use]
+    pub fn with_shading_normal(
+        distance: f32,
+        hit_point: Vec3,
+        direction: Vec3,
+        outward_normal: Vec3,
+        shading_normal: Vec3,
+        kind: IntersectKind,
+        material: Material,
+    ) -> Self {
+        let front_face = direction.dot(outward_normal) < 0.0;
+        Self {
+            distance,
+            hit_point: Some(hit_point),
+            normal: Some(if front_face {
+                shading_normal
+            } else {
+                -shading_normal
+            }),
+            front_face,
+            kind,
+            material: Some(material),
+        }
+    }
+
+    /// 光线没有打中任何物体.
+    #[must_use]
+    pub fn sky() -> Self {
+        Self {
+            distance: f32::INFINITY,
+            hit_point: None,
+            normal: None,
+            front_face: false,
+            kind: IntersectKind::Sky,
+            material: None,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Intersect {
+    #[must_use]
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    #[must_use]
+    pub fn hit_point(&self) -> Option<Vec3> {
+        self.hit_point
+    }
+
+    #[must_use]
+    pub fn normal(&self) -> Option<Vec3> {
+        self.normal
+    }
+
+    #[must_use]
+    pub fn front_face(&self) -> bool {
+        self.front_face
+    }
+
+    #[must_use]
+    pub fn kind(&self) -> IntersectKind {
+        self.kind
+    }
 }
 
-/// 会镜面反射的球体.
+/// 物体或者光源的句柄, 放入场景时返回, 删除其他物体之后仍然有效.
+///
+/// 物体和光源的句柄不会重复, 已经删除的句柄也不会再次使用.
+pub type Handle = u32;
+
+/// [`RayTracing::pick`] 的结果: 画面上某个像素看到的物体.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy)]
+pub struct PickResult {
+    /// 物体或者面光源的句柄, 地面没有句柄.
+    handle: Option<Handle>,
+    kind: IntersectKind,
+    hit_point: Vec3,
+    /// 法向量, 朝向相机一侧.
+    normal: Vec3,
+    distance: f32,
+}
+
+#[wasm_bindgen]
+impl PickResult {
+    #[must_use]
+    pub fn handle(&self) -> Option<Handle> {
+        self.handle
+    }
+
+    #[must_use]
+    pub fn kind(&self) -> IntersectKind {
+        self.kind
+    }
+
+    #[must_use]
+    pub fn hit_point(&self) -> Vec3 {
+        self.hit_point
+    }
+
+    #[must_use]
+    pub fn normal(&self) -> Vec3 {
+        self.normal
+    }
+
+    #[must_use]
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+}
+
+/// 球体, 默认是会镜面反射的灰色金属.
 #[wasm_bindgen]
 #[derive(Debug, Clone, Copy)]
 pub struct Sphere {
     center: Vec3,
     /// 球体半径 (米)
     radius: f32,
+    material: Material,
 }
 
 #[wasm_bindgen]
 impl Sphere {
     #[must_use]
     pub fn new(center: Vec3, radius: f32) -> Self {
-        Self { center, radius }
+        Self::with_material(center, radius, Material::default())
+    }
+
+    #[must_use]
+    pub fn with_material(center: Vec3, radius: f32, material: Material) -> Self {
+        Self {
+            center,
+            radius,
+            material,
+        }
+    }
+
+    #[must_use]
+    pub fn center(&self) -> Vec3 {
+        self.center
+    }
+
+    #[must_use]
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    #[must_use]
+    pub fn material(&self) -> Material {
+        self.material
+    }
+
+    pub fn set_center(&mut self, center: Vec3) {
+        self.center = center;
+    }
+
+    pub fn set_radius(&mut self, radius: f32) {
+        self.radius = radius;
+    }
+
+    pub fn set_material(&mut self, material: Material) {
+        self.material = material;
     }
 
     /// 球体和从某个点射出的光线求交. 起点在球面内上外估计都能正常计算.
@@ -87,22 +318,23 @@ impl Sphere {
         let frac_descriminant_4 = b * b - c;
         if frac_descriminant_4 > 0.0 {
             let mut distance = -b - frac_descriminant_4.sqrt();
-            let intersect_point = direction * distance + origin;
-            let mut normal = (intersect_point - self.center).normalize();
             if distance < 0.0 {
                 // 从球体内部射出的光线, 上面计算的距离可能是负数.
                 distance = -b + frac_descriminant_4.sqrt();
                 if distance < 0.0 {
                     return None;
                 }
-                normal = -normal; // 法向量向内.
             }
-            let intersect = Intersect {
+            let intersect_point = direction * distance + origin;
+            // 在球体内部时法向量会被翻转成向内.
+            let intersect = Intersect::new(
                 distance,
-                hit_point: Some(intersect_point),
-                normal: Some(normal),
-                kind: IntersectKind::Sphere,
-            };
+                intersect_point,
+                direction,
+                (intersect_point - self.center) / self.radius,
+                IntersectKind::Sphere,
+                self.material,
+            );
             Some(intersect)
         } else {
             None
@@ -110,43 +342,65 @@ impl Sphere {
     }
 }
 
-/// 点光源.
-#[wasm_bindgen]
-#[derive(Debug, Clone)]
-pub struct Light {
-    /// 光源位置.
-    pos: Vec3,
-    /// 光照强度 (0.0 ~ 1.0).
-    strength: f32,
-}
+impl Hittable for Sphere {
+    fn intersect(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<Intersect> {
+        Sphere::intersect(*self, origin, direction).filter(|i| i.distance < max_distance)
+    }
 
-#[wasm_bindgen]
-impl Light {
-    #[must_use]
-    pub fn new(pos: Vec3, strength: f32) -> Self {
-        Self { pos, strength }
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        Some(Aabb::new(self.center - r, self.center + r))
+    }
+
+    fn kind(&self) -> IntersectKind {
+        IntersectKind::Sphere
     }
 }
 
-/// 渲染一个 3D 场景(光线追踪), 地面为 z = 0.
+/// 渲染一个 3D 场景(光线追踪), 默认地面为 z = 0.
 #[wasm_bindgen]
 #[derive(Debug)]
 pub struct RayTracing {
     width: usize,
     height: usize,
     last_frame_time: Option<Instant>,
-    /// 摄像机世界坐标.
-    camera_pos: Vec3,
-    /// 摄像机视线, 始终是标准化的.
-    camera_gaze: Vec3,
-    /// 球体.
-    spheres: Vec<Sphere>,
+    /// 摄像机.
+    camera: Camera,
+    /// 场景中的物体.
+    objects: Vec<Box<dyn Hittable>>,
+    /// 和 `objects` 一一对应的句柄.
+    object_handles: Vec<Handle>,
+    /// 有包围盒的物体组成的 BVH, 场景变化之后为 None, 在下一次渲染前重建.
+    bvh: Option<Bvh>,
+    /// 没有包围盒 (无限大) 的物体下标, 和 BVH 一起重建.
+    unbounded_objects: Vec<usize>,
+    /// 地面, 没有地面时为 None.
+    ground: Option<Plane>,
     /// 光源.
     lights: Vec<Light>,
+    /// 和 `lights` 一一对应的句柄.
+    light_handles: Vec<Handle>,
+    /// 下一个分配的句柄.
+    next_handle: Handle,
+    /// 环境贴图, 优先于物理天空.
+    environment: Option<EnvironmentMap>,
+    /// 物理天空, 它的太阳光会加入光源.
+    /// 环境贴图和物理天空都没有时使用 [`RenderSettings::sky_color`] 的渐变天空.
+    physical_sky: Option<PhysicalSky>,
+    /// 渲染参数.
+    settings: RenderSettings,
     /// 按键管理器.
     am: ActionManager,
     rng: SmallRng,
     withdraw_actions_on_render: bool,
+    /// 渐进式渲染的累积缓冲区, 每个像素是各次渲染的线性颜色之和.
+    accumulation: Vec<Vec3>,
+    /// 累积缓冲区中已经累加的渲染次数.
+    accumulated_passes: u32,
+    /// 在画面上描出轮廓的物体, 见 [`set_highlight`](Self::set_highlight).
+    highlight: Option<Handle>,
+    /// 高亮物体覆盖的像素, 相机或者场景变化之后为 None, 在下一次输出画面时重新计算.
+    highlight_mask: Option<Vec<bool>>,
 }
 
 impl RayTracing {
@@ -154,28 +408,271 @@ impl RayTracing {
     const CAMERA_SPEED: f32 = 1.0;
     /// 相机转向速度 (rad/s).
     const CAMERA_ROTATION_SPEED: f32 = 30f32.to_radians();
-    /// 天空颜色.
-    const SKY_COLOR: Vec3 = Vec3::new_const(0.7, 0.6, 1.0);
-    /// 地面颜色 1.
-    const GROUND_COLOR_1: Vec3 = Vec3::new_const(0.9, 0.1, 0.1);
-    /// 地面颜色 2.
-    const GROUND_COLOR_2: Vec3 = Vec3::new_const(0.9, 0.9, 0.9);
-    /// 地面颜色格子大小 (米).
-    const GROUND_GRID_SIZE: f32 = 0.3;
-    /// 反射的亮度损耗.
-    const REFLECTION_DECAY: f32 = 0.4;
-    /// 高光幂次.
-    const SPECULAR_POW: f32 = 80.;
-    /// 焦平面大小 (米).
+    /// 鼠标移动一个像素相机转动的角度 (rad).
+    const MOUSE_SENSITIVITY: f32 = 0.003;
+    /// 滚轮每滚一格视野缩小的比例.
+    const ZOOM_PER_STEP: f32 = 1.1;
+    /// 高亮物体的轮廓颜色.
+    const HIGHLIGHT_COLOR: u32 = rgb(255, 200, 0);
+    /// 渲染时每一块的像素数, 每块使用独立的随机数.
+    const RENDER_CHUNK_SIZE: usize = 1000;
+
+    /// 遮挡检测: 从 `origin` 沿 `direction` (标准化) 射出的光线在 `max_distance` 之内是否被任何物体挡住.
+    ///
+    /// 和 [`intersect`](Self::intersect) 不同, 找到第一个遮挡物就会返回, 不需要求最近的交点.
+    #[must_use]
+    pub fn occluded(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> bool {
+        if let Some(ground) = &self.ground
+            && ground.occludes(origin, direction, max_distance)
+        {
+            return true;
+        }
+        let test = |i: usize| self.objects[i].occludes(origin, direction, max_distance);
+        match &self.bvh {
+            Some(bvh) => {
+                self.unbounded_objects.iter().any(|&i| test(i))
+                    || bvh.any_hit(origin, direction, max_distance, test)
+            }
+            None => (0..self.objects.len()).any(test),
+        }
+    }
+
+    /// 默认的地面: z = 0 处的棋盘格平面, 材质见 [`Material::default_ground`].
+    #[must_use]
+    pub fn default_ground() -> Plane {
+        Plane::new(Vec3::ZERO, Vec3::Z, Material::default_ground())
+    }
+
+    /// 根据场景描述创建渲染器.
+    pub fn from_scene(
+        scene: &Scene,
+        width: usize,
+        height: usize,
+        seed: u32,
+    ) -> Result<Self, String> {
+        let mut renderer = Self::new(width, height, seed);
+        renderer.camera = Camera::from(&scene.camera);
+        for light in &scene.lights {
+            renderer.put_light(light.into());
+        }
+        for object in &scene.objects {
+            renderer.put_scene_object(object)?;
+        }
+        renderer.ground = scene.ground.as_ref().map(Plane::from);
+        renderer.physical_sky = scene.sky.as_ref().map(PhysicalSky::from);
+        renderer.settings = scene.settings;
+        Ok(renderer)
+    }
+
+    /// 按描述创建物体并放入场景.
+    pub fn put_scene_object(&mut self, object: &ObjectDesc) -> Result<Handle, String> {
+        Ok(match object {
+            ObjectDesc::Sphere(sphere) => self.put_sphere(Sphere::from(sphere)),
+            ObjectDesc::Plane(plane) => self.put_plane(Plane::from(plane)),
+            ObjectDesc::Mesh(mesh) => self.put_mesh(Mesh::try_from(mesh)?),
+        })
+    }
+
+    /// 加入 glTF 场景中的物体和光源, 其中有相机时替换当前相机.
+    pub fn put_gltf(&mut self, scene: &GltfScene) -> Result<(), String> {
+        for object in &scene.objects {
+            self.put_scene_object(object)?;
+        }
+        for light in &scene.lights {
+            self.put_light(light.into());
+        }
+        if let Some(camera) = &scene.camera {
+            self.set_camera(camera.into());
+        }
+        Ok(())
+    }
+
+    /// 从文件导入 glTF 场景, 见 [`GltfScene::open`].
+    pub fn import_gltf_file(&mut self, path: impl AsRef<Path>) -> Result<(), String> {
+        self.put_gltf(&GltfScene::open(path)?)
+    }
+
+    /// 当前场景的描述, 不包括环境贴图和没有描述的自定义物体.
+    #[must_use]
+    pub fn to_scene(&self) -> Scene {
+        Scene {
+            camera: (&self.camera).into(),
+            lights: self.lights.iter().map(Into::into).collect(),
+            objects: self
+                .objects
+                .iter()
+                .filter_map(|object| ObjectDesc::from_object(object.as_ref()))
+                .collect(),
+            ground: self.ground.as_ref().map(Into::into),
+            sky: self.physical_sky.as_ref().map(Into::into),
+            settings: self.settings,
+        }
+    }
+
+    /// 放入任意实现了 [`Hittable`] 的物体.
+    pub fn put_object(&mut self, object: impl Hittable + 'static) -> Handle {
+        let handle = self.new_handle();
+        self.objects.push(Box::new(object));
+        self.object_handles.push(handle);
+        self.scene_changed();
+        handle
+    }
+
+    /// 把句柄对应的物体替换成 `object`, 句柄保持不变.
+    pub fn update_object(
+        &mut self,
+        handle: Handle,
+        object: impl Hittable + 'static,
+    ) -> Result<(), String> {
+        let i = self
+            .object_index(handle)
+            .ok_or_else(|| format!("no object with handle {handle}"))?;
+        self.objects[i] = Box::new(object);
+        self.scene_changed();
+        Ok(())
+    }
+
+    /// 场景中所有的物体和它们的句柄, 按放入的顺序.
+    pub fn objects(&self) -> impl Iterator<Item = (Handle, &dyn Hittable)> {
+        self.object_handles
+            .iter()
+            .copied()
+            .zip(self.objects.iter().map(AsRef::as_ref))
+    }
+
+    /// 场景中所有的光源和它们的句柄, 按放入的顺序.
+    pub fn lights(&self) -> impl Iterator<Item = (Handle, &Light)> {
+        self.light_handles.iter().copied().zip(&self.lights)
+    }
+
+    fn new_handle(&mut self) -> Handle {
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        handle
+    }
+
+    fn object_index(&self, handle: Handle) -> Option<usize> {
+        self.object_handles.iter().position(|&h| h == handle)
+    }
+
+    fn light_index(&self, handle: Handle) -> Option<usize> {
+        self.light_handles.iter().position(|&h| h == handle)
+    }
+
+    /// 物体发生变化, 需要重建 BVH 并重新渲染.
+    fn scene_changed(&mut self) {
+        self.bvh = None;
+        self.trigger_action(action::Action::RequestRender);
+    }
+
+    /// 当前画面每个像素的线性颜色 (没有经过曝光和色调映射), 按行存储.
+    pub fn hdr_frame(&self) -> impl Iterator<Item = Vec3> {
+        let passes = self.accumulated_passes.max(1) as f32;
+        self.accumulation.iter().map(move |&color| color / passes)
+    }
+
+    /// 当前画面经过后期处理之后的颜色 (0xAARRGGBB), 按行存储.
+    fn ldr_frame(&self) -> Vec<u32> {
+        self.hdr_frame()
+            .map(|color| tone_mapping::post_process(color, &self.settings))
+            .collect()
+    }
+
+    /// 显示用的画面: 后期处理之后再描出高亮物体的轮廓.
+    ///
+    /// 轮廓只用于显示, 不会出现在导出的图片中.
+    fn display_frame(&mut self) -> Vec<u32> {
+        let mut frame = self.ldr_frame();
+        if self.highlight_mask.is_none() {
+            self.highlight_mask = Some(self.compute_highlight_mask());
+        }
+        let Some(mask) = self.highlight_mask.as_ref().filter(|m| !m.is_empty()) else {
+            return frame;
+        };
+        let (width, height) = (self.width, self.height);
+        for y in 0..height {
+            for x in 0..width {
+                let i = y * width + x;
+                // 轮廓画在物体外面一圈, 不遮住物体本身.
+                let outline = !mask[i]
+                    && ((x > 0 && mask[i - 1])
+                        || (x + 1 < width && mask[i + 1])
+                        || (y > 0 && mask[i - width])
+                        || (y + 1 < height && mask[i + width]));
+                if outline {
+                    frame[i] = Self::HIGHLIGHT_COLOR;
+                }
+            }
+        }
+        frame
+    }
+
+    /// 每个像素的相机光线是否打中高亮物体, 没有高亮物体时为空.
+    fn compute_highlight_mask(&self) -> Vec<bool> {
+        let Some(highlight) = self.highlight else {
+            return Vec::new();
+        };
+        let view = self
+            .camera
+            .view_plane(self.width as f32 / self.height as f32);
+        let covered = |i: usize| {
+            let rx = ((i % self.width) as f32 + 0.5) / self.width as f32;
+            let ry = ((i / self.width) as f32 + 0.5) / self.height as f32;
+            self.camera
+                .primary_ray(&view, rx, ry)
+                .is_some_and(|(origin, direction)| {
+                    self.intersect_with_handle(origin, direction).1 == Some(highlight)
+                })
+        };
+        #[cfg(feature = "rayon")]
+        {
+            (0..self.height * self.width)
+                .into_par_iter()
+                .map(covered)
+                .collect()
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            (0..self.height * self.width).map(covered).collect()
+        }
+    }
+
+    /// 穿过画面上 (`x`, `y`) 处 (像素坐标, 可以是小数) 的相机光线, 返回起点和标准化的方向.
     ///
-    /// 焦平面的一半长度和 gaze 长度组成直角三角形两边,
-    /// 这个直角三角形以 gaze 边为轴进行对称, 得到的二倍角就是视场角:
-    /// `tan(FOV / 2) = FOCAL_SIZE / camera_gaze.magnitude()`
-    const FOCAL_SIZE: f32 = 2.5;
-    /// 抗锯齿采样次数.
-    const AA_SAMPLES: u16 = 5;
-    /// 最大的反射次数.
-    const MAX_REFLECTION: u32 = 3;
+    /// 和渲染时一样由相机发出, 不考虑景深, 没有光线的位置 (比如鱼眼画面的外圈) 返回 None.
+    #[must_use]
+    pub fn camera_ray(&self, x: f32, y: f32) -> Option<(Vec3, Vec3)> {
+        let view = self
+            .camera
+            .view_plane(self.width as f32 / self.height as f32);
+        self.camera
+            .primary_ray(&view, x / self.width as f32, y / self.height as f32)
+    }
+
+    /// 把当前画面保存到文件, 根据扩展名 (.ppm, .png, .exr) 决定格式.
+    pub fn save_image(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let format = ImageFormat::from_extension(path)
+            .ok_or_else(|| format!("unsupported image extension: {}", path.display()))?;
+        let bytes = self.export_image(format)?;
+        std::fs::write(path, bytes).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// 场景发生变化之后重建 BVH.
+    fn update_bvh(&mut self) {
+        if self.bvh.is_some() {
+            return;
+        }
+        let mut bounded = Vec::new();
+        self.unbounded_objects.clear();
+        for (i, object) in self.objects.iter().enumerate() {
+            match object.bounding_box() {
+                Some(bb) => bounded.push((i, bb)),
+                None => self.unbounded_objects.push(i),
+            }
+        }
+        self.bvh = Some(Bvh::build(bounded));
+    }
 }
 
 #[wasm_bindgen]
@@ -186,41 +683,242 @@ impl RayTracing {
             width,
             height,
             last_frame_time: None,
-            camera_pos: Vec3::new(0., 0., 0.),
-            camera_gaze: Vec3::new(1., 0., 0.).normalize(),
-            spheres: Vec::new(),
+            camera: Camera::default(),
+            objects: Vec::new(),
+            object_handles: Vec::new(),
+            bvh: None,
+            unbounded_objects: Vec::new(),
+            ground: Some(Self::default_ground()),
             lights: Vec::new(),
+            light_handles: Vec::new(),
+            next_handle: 0,
+            environment: None,
+            physical_sky: None,
+            settings: RenderSettings::default(),
             am: ActionManager::new(),
             rng: SmallRng::seed_from_u64(seed as u64),
             withdraw_actions_on_render: true,
+            accumulation: Vec::new(),
+            accumulated_passes: 0,
+            highlight: None,
+            highlight_mask: None,
         };
         self_.trigger_action(action::Action::RequestRender);
         self_
     }
 
+    /// 从 JSON 场景文件创建渲染器, 格式见 [`Scene`].
+    pub fn from_scene_str(
+        src: &str,
+        width: usize,
+        height: usize,
+        seed: u32,
+    ) -> Result<RayTracing, String> {
+        Self::from_scene(&Scene::from_json(src)?, width, height, seed)
+    }
+
+    /// 导入 glTF 文件内容 (.glb 或者缓冲区内嵌在 data URI 中的 .gltf), 见 [`GltfScene`].
+    pub fn import_gltf(&mut self, bytes: &[u8]) -> Result<(), String> {
+        let scene = GltfScene::parse(bytes, |uri| {
+            Err(format!(
+                "gltf: external buffer {uri:?} is not supported, use .glb instead"
+            ))
+        })?;
+        self.put_gltf(&scene)
+    }
+
+    /// 把当前场景保存成 JSON 场景文件.
+    pub fn to_scene_string(&self) -> Result<String, String> {
+        self.to_scene().to_json()
+    }
+
     fn delta_time(&self) -> Option<Duration> {
         self.last_frame_time.map(|x| x.elapsed())
     }
 
-    pub fn put_sphere(&mut self, sphere: Sphere) {
-        self.spheres.push(sphere);
+    pub fn put_sphere(&mut self, sphere: Sphere) -> Handle {
+        self.put_object(sphere)
+    }
+
+    pub fn put_plane(&mut self, plane: Plane) -> Handle {
+        self.put_object(plane)
     }
 
-    pub fn put_light(&mut self, light: Light) {
+    pub fn put_mesh(&mut self, mesh: Mesh) -> Handle {
+        self.put_object(mesh)
+    }
+
+    pub fn put_light(&mut self, light: Light) -> Handle {
+        let handle = self.new_handle();
         self.lights.push(light);
+        self.light_handles.push(handle);
+        self.trigger_action(action::Action::RequestRender);
+        handle
+    }
+
+    /// 删除句柄对应的物体或者光源, 句柄不存在时返回 false.
+    pub fn remove(&mut self, handle: Handle) -> bool {
+        if let Some(i) = self.object_index(handle) {
+            self.objects.remove(i);
+            self.object_handles.remove(i);
+            self.scene_changed();
+        } else if let Some(i) = self.light_index(handle) {
+            self.lights.remove(i);
+            self.light_handles.remove(i);
+            self.trigger_action(action::Action::RequestRender);
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// 删除所有的物体和光源, 地面, 天空和相机保持不变.
+    pub fn clear_scene(&mut self) {
+        self.objects.clear();
+        self.object_handles.clear();
+        self.lights.clear();
+        self.light_handles.clear();
+        self.scene_changed();
+    }
+
+    /// 所有物体的句柄, 按放入的顺序.
+    #[must_use]
+    pub fn object_handles(&self) -> Vec<Handle> {
+        self.object_handles.clone()
+    }
+
+    /// 所有光源的句柄, 按放入的顺序.
+    #[must_use]
+    pub fn light_handles(&self) -> Vec<Handle> {
+        self.light_handles.clone()
+    }
+
+    /// 句柄对应的物体的类型, 不是物体时返回 None, 见 [`Hittable::kind`].
+    #[must_use]
+    pub fn object_kind(&self, handle: Handle) -> Option<IntersectKind> {
+        Some(self.objects[self.object_index(handle)?].kind())
+    }
+
+    /// 句柄对应的球体, 不是球体时返回 None.
+    #[must_use]
+    pub fn sphere(&self, handle: Handle) -> Option<Sphere> {
+        let object: &dyn Any = self.objects[self.object_index(handle)?].as_ref();
+        object.downcast_ref::<Sphere>().copied()
+    }
+
+    /// 把句柄对应的球体替换成 `sphere`, 句柄对应的不是球体时返回错误.
+    pub fn update_sphere(&mut self, handle: Handle, sphere: Sphere) -> Result<(), String> {
+        if self.sphere(handle).is_none() {
+            return Err(format!("no sphere with handle {handle}"));
+        }
+        self.update_object(handle, sphere)
+    }
+
+    /// 句柄对应的光源, 不是光源时返回 None.
+    #[must_use]
+    pub fn light(&self, handle: Handle) -> Option<Light> {
+        Some(self.lights[self.light_index(handle)?].clone())
+    }
+
+    /// 把句柄对应的光源替换成 `light`, 句柄对应的不是光源时返回错误.
+    pub fn update_light(&mut self, handle: Handle, light: Light) -> Result<(), String> {
+        let i = self
+            .light_index(handle)
+            .ok_or_else(|| format!("no light with handle {handle}"))?;
+        self.lights[i] = light;
+        self.trigger_action(action::Action::RequestRender);
+        Ok(())
+    }
+
+    /// 设置地面, 传入 None (js 中的 undefined) 则去掉地面.
+    pub fn set_ground(&mut self, ground: Option<Plane>) {
+        self.ground = ground;
+        self.trigger_action(action::Action::RequestRender);
+    }
+
+    /// 地面, 没有地面时返回 None.
+    #[must_use]
+    pub fn ground(&self) -> Option<Plane> {
+        self.ground
+    }
+
+    /// 设置地面材质, 没有地面时会创建 z = 0 的地面.
+    pub fn set_ground_material(&mut self, material: Material) {
+        match &mut self.ground {
+            Some(ground) => ground.set_material(material),
+            None => self.ground = Some(Plane::new(Vec3::ZERO, Vec3::Z, material)),
+        }
+        self.trigger_action(action::Action::RequestRender);
+    }
+
+    /// 设置环境贴图, 它会作为背景, 并照亮反射和路径追踪.
+    /// 传入 None (js 中的 undefined) 则恢复渐变天空.
+    pub fn set_environment(&mut self, environment: Option<EnvironmentMap>) {
+        self.environment = environment;
+        self.trigger_action(action::Action::RequestRender);
+    }
+
+    /// 修改环境贴图绕 z 轴的旋转角 (角度制), 没有环境贴图时什么也不做.
+    pub fn set_environment_rotation(&mut self, rotation: f32) {
+        if let Some(environment) = &mut self.environment {
+            environment.set_rotation(rotation);
+            self.trigger_action(action::Action::RequestRender);
+        }
+    }
+
+    /// 设置物理天空, 它的太阳平行光会照亮场景并产生阴影.
+    /// 传入 None (js 中的 undefined) 则去掉物理天空和太阳光.
+    pub fn set_physical_sky(&mut self, sky: Option<PhysicalSky>) {
+        self.physical_sky = sky;
+        self.trigger_action(action::Action::RequestRender);
+    }
+
+    /// 按一天中的时间 (小时) 移动物理天空的太阳, 没有物理天空时什么也不做.
+    pub fn set_time_of_day(&mut self, hours: f32) {
+        if let Some(sky) = &mut self.physical_sky {
+            sky.set_time_of_day(hours);
+            self.trigger_action(action::Action::RequestRender);
+        }
+    }
+
+    #[must_use]
+    pub fn settings(&self) -> RenderSettings {
+        self.settings
+    }
+
+    /// 修改渲染参数, 会在下一次 render 时重新绘制.
+    pub fn set_settings(&mut self, settings: RenderSettings) {
+        self.settings = settings;
+        self.trigger_action(action::Action::RequestRender);
+    }
+
+    #[must_use]
+    pub fn camera(&self) -> Camera {
+        self.camera
+    }
+
+    /// 替换相机, 会在下一次 render 时重新绘制.
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.camera = camera;
+        self.trigger_action(action::Action::RequestRender);
     }
 
     pub fn move_camera_to(&mut self, pos: Vec3) {
-        self.camera_pos = pos;
+        self.camera.set_pos(pos);
+        self.trigger_action(action::Action::RequestRender);
     }
 
     pub fn rotate_camera_to(&mut self, gaze: Vec3) {
-        self.camera_gaze = gaze.normalize();
+        self.camera.set_gaze(gaze);
+        self.trigger_action(action::Action::RequestRender);
     }
 
     /// 在此处传入 [`RequestRender`](action::Action::RequestRender) 来让强制绘制一帧.
     ///
-    /// 触发的 action 在 render 之后就会被清空, 如果不想清空, 那么 `set_withdraw_actions_on_render(false)`.
+    /// 触发的 action 在 render 之后就会被清空, 如果不想清空, 那么 `set_withdraw_actions_on_render(false)`,
+    /// 但是 RequestRender 总是只生效一次.
+    ///
+    /// 修改场景 (`put_*`, `set_*`, 移动相机) 时会自动触发 RequestRender.
     pub fn trigger_action(&mut self, action: action::Action) {
         self.am.trigger(action);
     }
@@ -229,17 +927,35 @@ impl RayTracing {
         self.am.withdraw(action);
     }
 
+    /// 设置模拟量输入 (比如手柄摇杆), 取值 -1.0 ~ 1.0, 设置成 0 取消.
+    ///
+    /// 和 action 一样, 默认会在 render 之后清空, 见 [`trigger_action`](Self::trigger_action).
+    pub fn set_axis(&mut self, axis: Axis, value: f32) {
+        self.am.set_axis(axis, value);
+    }
+
+    /// 鼠标视角: 传入鼠标的移动量 (像素), 向右和向下为正, 在下一次 render 时转动相机.
+    pub fn look(&mut self, dx: f32, dy: f32) {
+        self.am.look(dx, dy);
+    }
+
+    /// 缩放视野, `steps` 是滚轮格数, 正数放大.
+    pub fn zoom(&mut self, steps: f32) {
+        self.am.zoom(steps);
+    }
+
     pub fn set_withdraw_actions_on_render(&mut self, value: bool) {
         self.withdraw_actions_on_render = value;
     }
 
     fn handle_actions(&mut self) {
-        debug_assert!(self.camera_gaze.is_normalized());
+        let gaze = self.camera.gaze();
+        debug_assert!(gaze.is_normalized());
         let delta_time = self.delta_time().map_or(f32::EPSILON, |x| x.as_secs_f32());
 
         // 计算相机转向.
         // yaw 表示和正 x 轴在 xy 平面内的夹角, 就像普通的数学角度一样.
-        let mut delta_angle = Vec3::ZERO; // x: yaw, y: pitch
+        let mut delta_angle = Vec3::ZERO; // x: yaw, y: pitch (按键方向)
         if self.am.is_triggerred(action::Action::CameraRotationDown) {
             delta_angle = delta_angle - Vec3::Y;
         }
@@ -250,40 +966,53 @@ impl RayTracing {
             delta_angle = delta_angle - Vec3::X;
         }
         if self.am.is_triggerred(action::Action::CameraRotationCW) {
-            delta_angle = delta_angle + Vec3::X; // 右转是增大 yaw (在右手坐标系中) ?
+            delta_angle = delta_angle + Vec3::X; // 画面的右方向是 up × gaze, 所以右转是增大 yaw.
+        }
+        if !delta_angle.is_zero() {
+            delta_angle = delta_angle.normalize();
         }
+        // 模拟量按比例转动, 鼠标按移动的像素转动.
+        delta_angle = (delta_angle
+            + Vec3::new(
+                self.am.axis(Axis::CameraTurnRight),
+                self.am.axis(Axis::CameraTurnUp),
+                0.,
+            ))
+            * (Self::CAMERA_ROTATION_SPEED * delta_time);
+        let (look_x, look_y) = self.am.take_look();
+        delta_angle = delta_angle + Vec3::new(look_x, -look_y, 0.) * Self::MOUSE_SENSITIVITY;
         if !delta_angle.is_zero() {
-            let horizontal_gaze = Vec3::new(self.camera_gaze.x, self.camera_gaze.y, 0.);
-            let current_yaw = self.camera_gaze.y.atan2(self.camera_gaze.x);
-            let current_pitch = self.camera_gaze.z.atan2(horizontal_gaze.magnitude());
+            let horizontal_gaze = Vec3::new(gaze.x, gaze.y, 0.);
+            let current_yaw = gaze.y.atan2(gaze.x);
+            let current_pitch = gaze.z.atan2(horizontal_gaze.magnitude());
             let Vec3 {
                 x: delta_yaw,
                 y: delta_pitch,
                 z: _,
-            } = delta_angle.normalize() * Self::CAMERA_ROTATION_SPEED * delta_time;
+            } = delta_angle;
 
             // 两个弧度
             let yaw = (current_yaw + delta_yaw).rem(2.0 * f32::consts::PI);
             let pitch =
                 (current_pitch + delta_pitch).clamp(-80f32.to_radians(), 80f32.to_radians()); // 限制角度防止万向轴问题.
             // 弧度转方向向量
-            self.camera_gaze = Vec3::new(
+            self.camera.set_gaze(Vec3::new(
                 yaw.cos() * pitch.cos(),
                 yaw.sin() * pitch.cos(),
                 pitch.sin(),
-            );
+            ));
         }
 
         // 计算相机坐标偏移.
         let delta_distance = Self::CAMERA_SPEED * delta_time;
         let mut direction = Vec3::ZERO;
-        // todo 判断这个叉积的方向是否正确.
-        let right_direction = Vec3::Z.cross(self.camera_gaze);
+        let gaze = self.camera.gaze();
+        let (right_direction, _) = self.camera.basis();
         if self.am.is_triggerred(action::Action::CameraMoveForward) {
-            direction = direction + self.camera_gaze;
+            direction = direction + gaze;
         }
         if self.am.is_triggerred(action::Action::CameraMoveBackward) {
-            direction = direction - self.camera_gaze;
+            direction = direction - gaze;
         }
         if self.am.is_triggerred(action::Action::CameraMoveLeft) {
             direction = direction - right_direction;
@@ -298,226 +1027,500 @@ impl RayTracing {
             direction = direction - Vec3::Z;
         }
         if !direction.is_zero() {
-            self.camera_pos = self.camera_pos + direction.normalize() * delta_distance;
+            direction = direction.normalize();
+        }
+        direction = direction
+            + gaze * self.am.axis(Axis::CameraMoveForward)
+            + right_direction * self.am.axis(Axis::CameraMoveRight)
+            + Vec3::Z * self.am.axis(Axis::CameraMoveUp);
+        if !direction.is_zero() {
+            // 斜着移动不会比最大速度更快.
+            let speed = direction.magnitude().min(1.);
+            self.camera
+                .set_pos(self.camera.pos() + direction.normalize() * speed * delta_distance);
+        }
+
+        // 缩放视野, 正交投影缩放画面高度.
+        let zoom = self.am.take_zoom();
+        if zoom != 0. {
+            let scale = Self::ZOOM_PER_STEP.powf(-zoom);
+            match self.camera.projection() {
+                Projection::Orthographic => self
+                    .camera
+                    .set_ortho_height(self.camera.ortho_height() * scale),
+                _ => self.camera.set_fov(self.camera.fov() * scale),
+            }
         }
     }
 
     /// 从一个点开始沿着指定方向进行相交检测, 返回相交结果.
     fn intersect(&self, origin: Vec3, direction: Vec3) -> Intersect {
-        let mut min_distance_intersect = Intersect {
-            distance: f32::INFINITY,
-            hit_point: None,
-            normal: None,
-            kind: IntersectKind::Sky,
-        };
+        self.intersect_with_handle(origin, direction).0
+    }
+
+    /// 和 [`intersect`](Self::intersect) 相同, 同时返回打中的物体 (或者面光源) 的句柄.
+    fn intersect_with_handle(&self, origin: Vec3, direction: Vec3) -> (Intersect, Option<Handle>) {
+        let mut min_distance_intersect = Intersect::sky();
+        let mut hit_handle = None;
 
         // 检测是否将会在某个远处相交于地面.
-        let t = -origin.z / direction.z;
-        if t > 0.0 {
-            min_distance_intersect = Intersect {
-                distance: t,
-                normal: Some(Vec3::Z),
-                hit_point: Some(origin + direction * t),
-                kind: IntersectKind::Ground,
-            };
+        if let Some(ground) = &self.ground
+            && let Some(mut intersect) =
+                Hittable::intersect(ground, origin, direction, f32::INFINITY)
+        {
+            intersect.kind = IntersectKind::Ground;
+            min_distance_intersect = intersect;
         }
 
-        // 和所有球体进行相交检测.
-        // if let Some(mdi_candidate) = self
-        //     .spheres
-        //     .iter()
-        //     .filter_map(|sphere| sphere.intersect(origin, direction))
-        //     .min_by(|ia, ib| ia.distance.partial_cmp(&ib.distance).unwrap())
-        //     && mdi_candidate.distance < min_distance_intersect.distance
-        // {
-        //     min_distance_intersect = mdi_candidate;
-        // }
-        // --- 上面是迭代器的写法, 下面是直接 for 的写法, 我发现下面更快一点. ---
-        for sphere in &self.spheres {
-            if let Some(intersect) = sphere.intersect(origin, direction)
-                && matches!(
-                    intersect
-                        .distance
-                        .partial_cmp(&min_distance_intersect.distance),
-                    Some(std::cmp::Ordering::Less)
-                )
-            {
+        // 和所有物体进行相交检测.
+        let mut max_distance = min_distance_intersect.distance;
+        let mut closer = |intersect: Option<Intersect>, handle: Handle, max_distance: f32| {
+            let intersect = intersect?;
+            (intersect.distance < max_distance).then(|| {
+                let distance = intersect.distance;
                 min_distance_intersect = intersect;
+                hit_handle = Some(handle);
+                distance
+            })
+        };
+        // 面光源对相机光线可见.
+        for (light, &handle) in self.lights.iter().zip(&self.light_handles) {
+            if let Some(t) = closer(light.intersect(origin, direction), handle, max_distance) {
+                max_distance = t;
+            }
+        }
+        let mut test = |i: usize, max_distance: f32| {
+            closer(
+                self.objects[i].intersect(origin, direction, max_distance),
+                self.object_handles[i],
+                max_distance,
+            )
+        };
+        match &self.bvh {
+            Some(bvh) => {
+                for &i in &self.unbounded_objects {
+                    if let Some(t) = test(i, max_distance) {
+                        max_distance = t;
+                    }
+                }
+                bvh.closest_hit(origin, direction, max_distance, test);
+            }
+            None => {
+                // BVH 还没建好, 逐个检测.
+                for i in 0..self.objects.len() {
+                    if let Some(t) = test(i, max_distance) {
+                        max_distance = t;
+                    }
+                }
             }
         }
 
-        min_distance_intersect
+        (min_distance_intersect, hit_handle)
     }
 
-    /// 着色, 返回颜色 rgb (0.0 ~ 1.0).
-    fn radiance(&self, origin: Vec3, direction: Vec3, reflection_count: u32) -> Vec3 {
-        let intersect = self.intersect(origin, direction);
-        if intersect.kind == IntersectKind::Sky {
-            return Self::SKY_COLOR * (1.0 - direction.z.abs()).powf(4.0);
+    /// 光线没有打中任何物体时看到的天空颜色.
+    fn sky(&self, direction: Vec3) -> Vec3 {
+        match &self.environment {
+            Some(environment) => environment.sample(direction),
+            None => match &self.physical_sky {
+                Some(sky) => sky.radiance(direction),
+                None => self.settings.sky_color * (1.0 - direction.z.abs()).powf(4.0),
+            },
         }
-        let intersect_point = intersect.hit_point.unwrap();
-        let normal = intersect.normal.unwrap();
+    }
 
-        // 计算各个点光源产生的兰伯特漫反射系数平均数.
-        let lambert = self
-            .lights
+    /// 参与直接光照的所有光源, 包括物理天空的太阳.
+    fn shading_lights(&self) -> impl Iterator<Item = &Light> {
+        self.lights
             .iter()
+            .chain(self.physical_sky.as_ref().map(|sky| &sky.sun))
+    }
+
+    /// 计算各个光源在某个点产生的兰伯特漫反射光照总和.
+    ///
+    /// 面光源每次随机选取光源上的一个点, 多次采样之后得到软阴影.
+    fn direct_light(&self, intersect_point: Vec3, normal: Vec3, rng: &mut SmallRng) -> Vec3 {
+        self.shading_lights()
             .map(|l| {
-                let to_light_direction = (l.pos - intersect_point).normalize();
-                let lambert = to_light_direction.dot(normal);
-                // 地上的点到点光源进行遮挡检测.
-                let it = self.intersect(intersect_point, to_light_direction);
-                if matches!(it.kind, IntersectKind::Sphere) {
+                let sample = l.sample(intersect_point, rng);
+                let lambert = sample.direction.dot(normal);
+                // 背对光源, 不需要进行遮挡检测.
+                if lambert <= 0.0 || sample.radiance.is_zero() {
+                    return Vec3::ZERO;
+                }
+                // 到光源进行遮挡检测, 光源后面的物体不会产生遮挡.
+                if self.occluded(
+                    intersect_point + normal * 0.01,
+                    sample.direction,
+                    sample.distance,
+                ) {
                     // 被遮挡了.
-                    0.
+                    Vec3::ZERO
                 } else {
-                    lambert * l.strength
+                    sample.radiance * lambert
                 }
             })
-            .sum::<f32>()
-            .div(self.lights.len() as f32)
-            .max(0.);
-
-        match intersect.kind {
-            IntersectKind::Ground => {
-                // 根据格子坐标选择颜色.
-                let Vec3 { x, y, z: _ } = intersect_point;
-                let ground_color = if ((x / Self::GROUND_GRID_SIZE).floor() as i32
-                    + (y / Self::GROUND_GRID_SIZE).floor() as i32)
-                    % 2
-                    == 0
-                {
-                    Self::GROUND_COLOR_1
-                } else {
-                    Self::GROUND_COLOR_2
-                };
-                ground_color * (lambert + 0.1)
+            .fold(Vec3::ZERO, Add::add)
+    }
+
+    /// 着色, 返回颜色 rgb (0.0 ~ 1.0).
+    fn radiance(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        reflection_count: u32,
+        rng: &mut SmallRng,
+    ) -> Vec3 {
+        let intersect = self.intersect(origin, direction);
+        let Some(material) = intersect.material else {
+            return self.sky(direction);
+        };
+        if material.kind() == MaterialKind::Emissive {
+            return material.emission();
+        }
+        let intersect_point = intersect.hit_point.unwrap();
+        let normal = intersect.normal.unwrap();
+        let direct = self.direct_light(intersect_point, normal, rng);
+
+        match material.kind() {
+            MaterialKind::Diffuse => {
+                material.albedo_at(intersect_point) * (direct + Vec3::new(0.1, 0.1, 0.1))
             }
-            IntersectKind::Sphere => {
-                let reflect_direction = normal * (2.0 * normal.dot(-direction)) + direction;
-                // 计算高光(所有亮度产生的高光总和).
-                let specular = if lambert > 0.0 {
-                    self.lights
-                        .iter()
-                        .map(|l| {
-                            let to_light_direction = (l.pos - intersect_point).normalize();
-                            reflect_direction
-                                .dot(to_light_direction)
-                                .powf(Self::SPECULAR_POW)
-                                * l.strength
-                        })
-                        .sum::<f32>()
-                        .clamp(0.0, 1.0)
-                } else {
-                    0.
-                };
-                Vec3::new(specular, specular, specular)
-                    + if reflection_count <= Self::MAX_REFLECTION {
-                        // + normal * 0.01 防止又检测到此球体.
+            MaterialKind::Metal => {
+                let reflect_direction = direction.reflect(normal);
+                self.specular(intersect_point, reflect_direction, direct, rng)
+                    + if reflection_count <= self.settings.max_reflection {
+                        // + normal * 0.01 防止又检测到此物体.
                         self.radiance(
                             intersect_point + normal * 0.01,
                             reflect_direction,
                             reflection_count + 1,
-                        ) * (1.0 - Self::REFLECTION_DECAY)
+                            rng,
+                        ) * material.albedo_at(intersect_point)
                     } else {
                         Vec3::ZERO
                     }
             }
-            IntersectKind::Sky => unreachable!(),
+            MaterialKind::Dielectric => {
+                let reflect_direction = direction.reflect(normal);
+                let specular = self.specular(intersect_point, reflect_direction, direct, rng);
+                if reflection_count > self.settings.max_reflection {
+                    return specular;
+                }
+                let reflected = self.radiance(
+                    intersect_point + normal * 0.01,
+                    reflect_direction,
+                    reflection_count + 1,
+                    rng,
+                );
+                let Some((refract_direction, fresnel)) =
+                    material.refract(direction, normal, intersect.front_face)
+                else {
+                    // 全反射.
+                    return specular + reflected;
+                };
+                let refracted = self.radiance(
+                    intersect_point - normal * 0.01,
+                    refract_direction,
+                    reflection_count + 1,
+                    rng,
+                );
+                specular
+                    + reflected * fresnel
+                    + refracted * material.albedo_at(intersect_point) * (1.0 - fresnel)
+            }
+            MaterialKind::Emissive => unreachable!(),
+        }
+    }
+
+    /// 计算高光 (所有光源产生的高光总和), 每个颜色分量限制在 0.0 ~ 1.0.
+    fn specular(
+        &self,
+        intersect_point: Vec3,
+        reflect_direction: Vec3,
+        direct: Vec3,
+        rng: &mut SmallRng,
+    ) -> Vec3 {
+        if direct.is_zero() {
+            return Vec3::ZERO;
+        }
+        let specular = self
+            .shading_lights()
+            .map(|l| {
+                let sample = l.sample(intersect_point, rng);
+                sample.radiance
+                    * reflect_direction
+                        .dot(sample.direction)
+                        .max(0.0)
+                        .powf(self.settings.specular_pow)
+            })
+            .fold(Vec3::ZERO, Add::add);
+        Vec3::new(
+            specular.x.clamp(0.0, 1.0),
+            specular.y.clamp(0.0, 1.0),
+            specular.z.clamp(0.0, 1.0),
+        )
+    }
+
+    /// 当前的对焦距离, 开启自动对焦时取画面中心看到的物体沿视线方向的距离.
+    fn focus_distance(&self) -> f32 {
+        if self.settings.autofocus {
+            let intersect = self.intersect(self.camera.pos(), self.camera.gaze());
+            if intersect.material.is_some() {
+                return intersect.distance;
+            }
+        }
+        self.settings.focus_distance
+    }
+
+    /// 渲染一遍所有像素, 返回每个像素的线性颜色.
+    fn render_pass(&mut self) -> Vec<Vec3> {
+        // 每一遍使用不同的随机数, 渐进式渲染才能收敛.
+        let pass_seed: u64 = self.rng.random();
+        let view = self
+            .camera
+            .view_plane(self.width as f32 / self.height as f32);
+        let focus_distance = self.focus_distance();
+        let view = &view;
+        let this = &*self;
+        let pixel_count = self.height * self.width;
+        let chunk_count = pixel_count.div_ceil(Self::RENDER_CHUNK_SIZE);
+        // 每一块的随机数只由 (pass_seed, 块下标) 决定,
+        // 所以结果和线程调度无关, 开不开 rayon 渲染结果都一样.
+        let render_chunk = |chunk: usize| {
+            let mut rng = SmallRng::seed_from_u64(mix_seed(pass_seed, chunk as u64));
+            let start = chunk * Self::RENDER_CHUNK_SIZE;
+            let end = (start + Self::RENDER_CHUNK_SIZE).min(pixel_count);
+            (start..end).map(move |i| this.render_pixel(i, view, focus_distance, &mut rng))
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            (0..chunk_count)
+                .into_par_iter()
+                .flat_map_iter(render_chunk)
+                .collect()
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            (0..chunk_count).flat_map(render_chunk).collect()
         }
     }
 
-    #[allow(clippy::too_many_arguments)]
     fn render_pixel(
         &self,
         i: usize,
-        right: Vec3,
-        down: Vec3,
-        top_left: Vec3,
-        interval_x: f32,
-        interval_y: f32,
+        view: &ViewPlane,
+        focus_distance: f32,
         rng: &mut SmallRng,
-    ) -> u32 {
-        let x = i % self.width;
-        let y = i / self.width;
-        let rx = x as f32 / self.width as f32;
-        let ry = y as f32 / self.height as f32;
+    ) -> Vec3 {
+        let x = (i % self.width) as f32;
+        let y = (i / self.width) as f32;
+        let camera_pos = self.camera.pos();
+        let gaze = self.camera.gaze();
         let mut pixel_color = Vec3::ZERO;
-        for _ in 0..Self::AA_SAMPLES {
-            // todo 不知道这个景深随机值取什么范围比较好.
-            let dof_src =
-                right * rng.random_range(0.0..0.001) + down * rng.random_range(0.0..0.001);
-            let direction = top_left - dof_src
-                + right * (rx * Self::FOCAL_SIZE + rng.random_range(-interval_x..interval_x))
-                + down * (ry * Self::FOCAL_SIZE + rng.random_range(-interval_y..interval_y));
-            let direction = direction.normalize();
-            pixel_color = pixel_color + self.radiance(self.camera_pos + dof_src, direction, 0);
+        let samples = self.settings.aa_samples.max(1);
+        for _ in 0..samples {
+            // 在像素范围内随机取一点抗锯齿.
+            let rx = (x + rng.random::<f32>()) / self.width as f32;
+            let ry = (y + rng.random::<f32>()) / self.height as f32;
+            let Some((camera_origin, camera_direction)) = self.camera.primary_ray(view, rx, ry)
+            else {
+                // 没有光线的像素 (比如鱼眼画面的外圈) 是黑色.
+                continue;
+            };
+            let (origin, direction) = if self.settings.aperture > 0.0
+                && self.camera.projection() == Projection::Perspective
+            {
+                // 薄透镜: 在透镜圆盘上随机取一点, 射向针孔光线和对焦平面的交点,
+                // 对焦平面上的点始终清晰, 离得越远越模糊.
+                let focus_point =
+                    camera_pos + camera_direction * (focus_distance / camera_direction.dot(gaze));
+                let (lens_x, lens_y) = sampling::unit_disk(rng);
+                let origin = camera_pos
+                    + (view.right * lens_x + view.down * lens_y) * self.settings.aperture;
+                (origin, (focus_point - origin).normalize())
+            } else {
+                (camera_origin, camera_direction)
+            };
+            pixel_color = pixel_color
+                + match self.settings.integrator {
+                    Integrator::Whitted => self.radiance(origin, direction, 0, rng),
+                    Integrator::PathTracing => self.path_trace(origin, direction, rng),
+                };
         }
-        pixel_color = pixel_color / Self::AA_SAMPLES.into();
-        rgbf(pixel_color.x, pixel_color.y, pixel_color.z)
+        pixel_color / samples.into()
     }
 
-    /// 渲染画面, 当没有任何操作 ([`Action`](action::Action)) 的时候, 画面没变, 返回 None.
+    /// 渲染画面.
+    ///
+    /// 当没有任何操作 ([`Action`](action::Action)) 的时候, 画面没变,
+    /// 如果开启了渐进式渲染 ([`RenderSettings::progressive`]) 会继续采样并返回累积的平均结果,
+    /// 直到达到 [`RenderSettings::max_progressive_passes`] 次之后返回 None.
     pub fn render(&mut self) -> Option<Vec<u32>> {
-        if !self.am.has_actions() {
-            // 没操作, 那么场景没有变化, 不渲染.
+        if self.am.has_actions() {
+            self.handle_actions();
+            // RequestRender 只请求一次渲染.
+            self.am.withdraw(action::Action::RequestRender);
+            if self.withdraw_actions_on_render {
+                self.am.clear();
+            }
+            // 相机或者场景变了, 之前累积的结果作废.
+            self.accumulated_passes = 0;
+            self.highlight_mask = None;
+        } else if !self.settings.progressive
+            || self.accumulated_passes >= self.settings.max_progressive_passes
+        {
+            // 没操作, 那么场景没有变化, 并且已经不需要继续累积了, 不渲染.
             self.last_frame_time = Some(Instant::now()); // 假装渲染了一帧便于后面的时间计算.
+            // 只是高亮的物体变了, 重新描轮廓就行.
+            if self.highlight_mask.is_none() && !self.accumulation.is_empty() {
+                return Some(self.display_frame());
+            }
             return None;
         }
-        self.handle_actions();
-        if self.withdraw_actions_on_render {
-            self.am.clear();
-        }
         self.last_frame_time = Some(Instant::now());
-        // todo 判断这个叉积的方向是否正确.
-        let right = Vec3::Z.cross(self.camera_gaze).normalize();
-        let down = right.cross(self.camera_gaze).normalize();
-        // 焦平面左上.
-        let top_left =
-            -right * Self::FOCAL_SIZE / 2.0 - down * Self::FOCAL_SIZE / 2.0 + self.camera_gaze;
+        self.update_bvh();
 
-        let interval_x = 0.5 / self.width as f32;
-        let interval_y = 0.5 / self.height as f32;
+        let pass = self.render_pass();
+        if self.accumulated_passes == 0 {
+            self.accumulation = pass;
+        } else {
+            self.accumulation
+                .iter_mut()
+                .zip(pass)
+                .for_each(|(acc, color)| *acc = *acc + color);
+        }
+        self.accumulated_passes += 1;
 
-        #[cfg(feature = "rayon")]
-        {
-            Some(
-                (0..self.height * self.width)
-                    .into_par_iter()
-                    .chunks(1000)
-                    .map_init(
-                        || self.rng.clone(),
-                        |rng, chunk| {
-                            chunk
-                                .into_iter()
-                                .map(|i| {
-                                    self.render_pixel(
-                                        i, right, down, top_left, interval_x, interval_y, rng,
-                                    )
-                                })
-                                .collect::<Vec<_>>()
-                        },
-                    )
-                    .flatten()
-                    .collect(),
-            )
+        Some(self.display_frame())
+    }
+
+    /// 把当前画面编码成图片, 用于下载或者保存.
+    ///
+    /// PPM 和 PNG 是经过后期处理的画面, EXR 是线性颜色.
+    pub fn export_image(&self, format: ImageFormat) -> Result<Vec<u8>, String> {
+        if self.accumulation.is_empty() {
+            return Err("no frame has been rendered yet".into());
         }
-        #[cfg(not(feature = "rayon"))]
-        {
-            let rng = &mut self.rng.clone();
-            Some(
-                (0..self.height * self.width)
-                    .map(|i| {
-                        self.render_pixel(i, right, down, top_left, interval_x, interval_y, rng)
-                    })
-                    .collect(),
-            )
+        match format {
+            ImageFormat::Ppm => Ok(export::encode_ppm(
+                self.width,
+                self.height,
+                &self.ldr_frame(),
+            )),
+            ImageFormat::Png => export::encode_png(self.width, self.height, &self.ldr_frame()),
+            ImageFormat::Exr => export::encode_exr(
+                self.width,
+                self.height,
+                &self.hdr_frame().collect::<Vec<_>>(),
+            ),
         }
     }
 
+    /// 已经累积的渲染次数, 每次有 [`AA 采样`](RenderSettings::aa_samples) 个样本.
+    #[must_use]
+    pub fn accumulated_passes(&self) -> u32 {
+        self.accumulated_passes
+    }
+
+    /// 选取画面上第 `y` 行第 `x` 列像素看到的物体.
+    ///
+    /// 光线是穿过像素中心的 [`camera_ray`](Self::camera_ray).
+    /// 像素在画面之外或者看到的是天空时返回 None.
+    #[must_use]
+    pub fn pick(&self, x: usize, y: usize) -> Option<PickResult> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let (origin, direction) = self.camera_ray(x as f32 + 0.5, y as f32 + 0.5)?;
+        let (intersect, handle) = self.intersect_with_handle(origin, direction);
+        Some(PickResult {
+            handle,
+            kind: intersect.kind,
+            hit_point: intersect.hit_point?,
+            normal: intersect.normal?,
+            distance: intersect.distance,
+        })
+    }
+
+    /// 在画面上描出物体或者面光源的轮廓, 传入 None (js 中的 undefined) 取消高亮.
+    pub fn set_highlight(&mut self, handle: Option<Handle>) {
+        if self.highlight != handle {
+            self.highlight = handle;
+            self.highlight_mask = None;
+        }
+    }
+
+    #[must_use]
+    pub fn highlight(&self) -> Option<Handle> {
+        self.highlight
+    }
+
     #[cfg(target_arch = "wasm32")]
     pub fn render_to_web_color(&mut self) -> Option<Vec<u8>> {
         // todo 找一个零拷贝传递给 js 的方法.
         Some(self.render()?.into_iter().flat_map(to_web_color).collect())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray_tracing::mesh::Triangle;
+
+    #[test]
+    fn render_pass_is_deterministic() {
+        let render = || {
+            let mut ray_tracing = RayTracing::new(40, 30, 7);
+            ray_tracing.set_settings(RenderSettings {
+                integrator: Integrator::PathTracing,
+                ..ray_tracing.settings()
+            });
+            ray_tracing.put_object(Sphere::new(Vec3::new(3., 0., 1.), 1.));
+            (0..2)
+                .flat_map(|_| ray_tracing.render_pass())
+                .collect::<Vec<_>>()
+        };
+        let bits = |pixels: Vec<Vec3>| {
+            pixels
+                .into_iter()
+                .flat_map(|p| [p.x, p.y, p.z].map(f32::to_bits))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(bits(render()), bits(render()));
+    }
+
+    #[test]
+    fn screen_right_is_up_cross_gaze() {
+        // 沿 +x 看, z 轴向上时 +y 在画面右半边, 和最初的实现一致.
+        let mut ray_tracing = RayTracing::new(40, 20, 0);
+        let mut camera = Camera::look_at(Vec3::Z, Vec3::new(5., 0., 1.), Vec3::Z);
+        camera.set_fov(40.);
+        ray_tracing.set_camera(camera);
+        ray_tracing.set_ground(None);
+        let sphere = ray_tracing.put_object(Sphere::new(Vec3::new(5., 2., 1.), 1.));
+        assert_eq!(
+            ray_tracing.pick(31, 10).and_then(|p| p.handle),
+            Some(sphere)
+        );
+        assert!(ray_tracing.pick(9, 10).is_none());
+    }
+
+    #[test]
+    fn sphere_by_handle() {
+        let mut ray_tracing = RayTracing::new(4, 4, 0);
+        let sphere = ray_tracing.put_object(Sphere::new(Vec3::new(1., 2., 3.), 0.5));
+        let mesh = ray_tracing.put_object(Mesh::new(
+            vec![Triangle::new([Vec3::ZERO, Vec3::X, Vec3::Y])],
+            Material::diffuse(Vec3::new(1., 1., 1.)),
+        ));
+        assert_eq!(ray_tracing.sphere(sphere).unwrap().radius(), 0.5);
+        assert!(ray_tracing.sphere(mesh).is_none());
+        assert!(
+            ray_tracing
+                .update_sphere(mesh, Sphere::new(Vec3::ZERO, 1.))
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use std::f32;
+
+use rand::Rng;
+use rand::rngs::SmallRng;
+
+use crate::ray_tracing::material::MaterialKind;
+use crate::ray_tracing::sampling;
+use crate::ray_tracing::vector::Vec3;
+use crate::ray_tracing::{IntersectKind, RayTracing};
+
+impl RayTracing {
+    /// 从第几次弹射开始进行俄罗斯轮盘赌.
+    const RUSSIAN_ROULETTE_DEPTH: u32 = 3;
+
+    /// 蒙特卡洛路径追踪, 返回这条光线带回来的颜色 (一个样本).
+    ///
+    /// 漫反射表面使用余弦加权的半球采样来继续路径,
+    /// 同时对点光源做直接光照采样 (next event estimation),
+    /// 镜面和电介质按照材质的反射/折射方向继续.
+    pub(super) fn path_trace(&self, origin: Vec3, direction: Vec3, rng: &mut SmallRng) -> Vec3 {
+        let mut color = Vec3::ZERO;
+        // 路径上累积的衰减.
+        let mut throughput = Vec3::new(1., 1., 1.);
+        let mut origin = origin;
+        let mut direction = direction;
+        // 上一次是否是镜面反射 (或者是相机光线), 漫反射之后打中面光源不计入,
+        // 因为直接光照已经采样过面光源了.
+        let mut specular_bounce = true;
+
+        for depth in 0..self.settings.max_bounces.max(1) {
+            let intersect = self.intersect(origin, direction);
+            let Some(material) = intersect.material else {
+                color = color + throughput * self.sky(direction);
+                break;
+            };
+            let intersect_point = intersect.hit_point.unwrap();
+            let normal = intersect.normal.unwrap();
+
+            match material.kind() {
+                MaterialKind::Emissive => {
+                    // 自发光物体不会被直接光照采样, 所以不会被重复计算.
+                    if specular_bounce || intersect.kind != IntersectKind::Light {
+                        color = color + throughput * material.emission();
+                    }
+                    break;
+                }
+                MaterialKind::Diffuse => {
+                    let albedo = material.albedo_at(intersect_point);
+                    // 直接光照: 朗伯 brdf 是 albedo / π, 和下面的间接光照保持一致.
+                    color = color
+                        + throughput
+                            * albedo
+                            * self.direct_light(intersect_point, normal, rng)
+                            * f32::consts::FRAC_1_PI;
+                    // 间接光照: 余弦加权采样时 brdf * cos / pdf 正好是 albedo.
+                    throughput = throughput * albedo;
+                    origin = intersect_point + normal * 0.01;
+                    direction = sampling::cosine_hemisphere(normal, rng);
+                    specular_bounce = false;
+                }
+                MaterialKind::Metal => {
+                    throughput = throughput * material.albedo_at(intersect_point);
+                    origin = intersect_point + normal * 0.01;
+                    direction = direction.reflect(normal);
+                    specular_bounce = true;
+                }
+                MaterialKind::Dielectric => {
+                    specular_bounce = true;
+                    match material.refract(direction, normal, intersect.front_face) {
+                        // 按菲涅尔反射率随机选择折射还是反射.
+                        Some((refract_direction, fresnel)) if rng.random::<f32>() >= fresnel => {
+                            throughput = throughput * material.albedo_at(intersect_point);
+                            origin = intersect_point - normal * 0.01;
+                            direction = refract_direction;
+                        }
+                        _ => {
+                            origin = intersect_point + normal * 0.01;
+                            direction = direction.reflect(normal);
+                        }
+                    }
+                }
+            }
+
+            // 俄罗斯轮盘赌: 贡献越小的路径越容易被终止, 存活的路径补偿能量保证无偏.
+            if depth >= Self::RUSSIAN_ROULETTE_DEPTH {
+                let survive = throughput
+                    .x
+                    .max(throughput.y)
+                    .max(throughput.z)
+                    .clamp(0.05, 1.0);
+                if rng.random::<f32>() >= survive {
+                    break;
+                }
+                throughput = throughput / survive;
+            }
+        }
+        color
+    }
+}
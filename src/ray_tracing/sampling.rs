@@ -0,0 +1,35 @@
+use std::f32;
+
+use rand::Rng;
+
+use crate::ray_tracing::vector::Vec3;
+
+/// 以标准化的 `normal` 为 z 轴构建一组正交基, 返回另外两个轴.
+#[must_use]
+pub fn orthonormal_basis(normal: Vec3) -> (Vec3, Vec3) {
+    let helper = if normal.x.abs() > 0.9 {
+        Vec3::Y
+    } else {
+        Vec3::X
+    };
+    let tangent = helper.cross(normal).normalize();
+    let bitangent = normal.cross(tangent);
+    (tangent, bitangent)
+}
+
+/// 单位圆盘上的均匀采样, 返回 (x, y).
+#[must_use]
+pub fn unit_disk(rng: &mut impl Rng) -> (f32, f32) {
+    let r = rng.random::<f32>().sqrt();
+    let theta = 2.0 * f32::consts::PI * rng.random::<f32>();
+    (r * theta.cos(), r * theta.sin())
+}
+
+/// 以 `normal` 为中心的半球上按余弦加权采样 (pdf = cos θ / π).
+#[must_use]
+pub fn cosine_hemisphere(normal: Vec3, rng: &mut impl Rng) -> Vec3 {
+    let (x, y) = unit_disk(rng);
+    let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+    let (tangent, bitangent) = orthonormal_basis(normal);
+    (tangent * x + bitangent * y + normal * z).normalize()
+}
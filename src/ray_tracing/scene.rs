@@ -0,0 +1,533 @@
+use std::any::Any;
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ray_tracing::camera::{Camera, Projection};
+use crate::ray_tracing::hittable::{Hittable, Plane};
+use crate::ray_tracing::light::{Light, LightKind};
+use crate::ray_tracing::material::{Material, MaterialKind};
+use crate::ray_tracing::mesh::{Mesh, Triangle};
+use crate::ray_tracing::settings::RenderSettings;
+use crate::ray_tracing::sky::PhysicalSky;
+use crate::ray_tracing::vector::Vec3;
+use crate::ray_tracing::{RayTracing, Sphere};
+
+/// 场景描述, 对应一个 JSON 场景文件:
+///
+/// ```json
+/// {
+///     "camera": { "pos": [0, 0, 4], "look_at": [1, 0, 1] },
+///     "lights": [{ "type": "point", "pos": [5, -5, 3], "strength": 30 }],
+///     "objects": [{ "type": "sphere", "center": [0, 0, 1], "radius": 1,
+///                   "material": { "type": "metal", "albedo": [0.6, 0.6, 0.6] } }],
+///     "settings": { "integrator": "path_tracing" }
+/// }
+/// ```
+///
+/// 所有字段都可以省略, 省略时和 [`RayTracing::new`] 一致 (`objects` 省略时场景中没有物体).
+/// 环境贴图是图片数据, 不保存在场景文件中.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scene {
+    #[serde(default)]
+    pub camera: CameraDesc,
+    #[serde(default)]
+    pub lights: Vec<LightDesc>,
+    #[serde(default)]
+    pub objects: Vec<ObjectDesc>,
+    /// 地面, `null` 表示没有地面, 省略时是默认的棋盘格地面.
+    #[serde(default = "default_ground")]
+    pub ground: Option<PlaneDesc>,
+    /// 物理天空, 省略时使用渐变天空.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sky: Option<SkyDesc>,
+    #[serde(default)]
+    pub settings: RenderSettings,
+}
+
+fn default_ground() -> Option<PlaneDesc> {
+    Some(PlaneDesc::from(&RayTracing::default_ground()))
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self {
+            camera: CameraDesc::default(),
+            lights: Vec::new(),
+            objects: Vec::new(),
+            ground: default_ground(),
+            sky: None,
+            settings: RenderSettings::default(),
+        }
+    }
+}
+
+impl Scene {
+    /// 解析 JSON 场景文件.
+    pub fn from_json(src: &str) -> Result<Self, String> {
+        serde_json::from_str(src).map_err(|e| format!("scene: {e}"))
+    }
+
+    /// 序列化成带缩进的 JSON.
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| format!("scene: {e}"))
+    }
+}
+
+/// 相机, 省略的字段和 [`Camera::default`] 一致.
+///
+/// 给出 `look_at` 时忽略 `gaze`, 相机看向这个点.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraDesc {
+    pub pos: Vec3,
+    pub gaze: Vec3,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub look_at: Option<Vec3>,
+    pub up: Vec3,
+    pub roll: f32,
+    pub fov: f32,
+    pub projection: Projection,
+    pub ortho_height: f32,
+}
+
+impl Default for CameraDesc {
+    fn default() -> Self {
+        Self::from(&Camera::default())
+    }
+}
+
+impl From<&Camera> for CameraDesc {
+    fn from(camera: &Camera) -> Self {
+        Self {
+            pos: camera.pos(),
+            gaze: camera.gaze(),
+            look_at: None,
+            up: camera.up(),
+            roll: camera.roll(),
+            fov: camera.fov(),
+            projection: camera.projection(),
+            ortho_height: camera.ortho_height(),
+        }
+    }
+}
+
+impl From<&CameraDesc> for Camera {
+    fn from(desc: &CameraDesc) -> Self {
+        let mut camera = match desc.look_at {
+            Some(target) => Camera::look_at(desc.pos, target, desc.up),
+            None => {
+                let mut camera = Camera::new(desc.pos, desc.gaze);
+                camera.set_up(desc.up);
+                camera
+            }
+        };
+        camera.set_roll(desc.roll);
+        camera.set_fov(desc.fov);
+        camera.set_projection(desc.projection);
+        camera.set_ortho_height(desc.ortho_height);
+        camera
+    }
+}
+
+fn white() -> Vec3 {
+    Vec3::new(1., 1., 1.)
+}
+
+/// 光源, 形状相关的字段见 [`LightShape`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct LightDesc {
+    #[serde(flatten)]
+    pub shape: LightShape,
+    pub strength: f32,
+    #[serde(default = "white")]
+    pub color: Vec3,
+}
+
+/// 光源的种类和形状, 参数和 [`Light`] 的各个构造函数一致.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LightShape {
+    Point {
+        pos: Vec3,
+    },
+    Directional {
+        direction: Vec3,
+    },
+    Spot {
+        pos: Vec3,
+        direction: Vec3,
+        inner_angle: f32,
+        outer_angle: f32,
+    },
+    Sphere {
+        center: Vec3,
+        radius: f32,
+    },
+    Rect {
+        center: Vec3,
+        edge_u: Vec3,
+        edge_v: Vec3,
+    },
+}
+
+impl From<&Light> for LightDesc {
+    fn from(light: &Light) -> Self {
+        let shape = match light.kind() {
+            LightKind::Point => LightShape::Point { pos: light.pos() },
+            LightKind::Directional => LightShape::Directional {
+                direction: light.direction(),
+            },
+            LightKind::Spot => LightShape::Spot {
+                pos: light.pos(),
+                direction: light.direction(),
+                inner_angle: light.inner_angle(),
+                outer_angle: light.outer_angle(),
+            },
+            LightKind::Sphere => LightShape::Sphere {
+                center: light.pos(),
+                radius: light.radius(),
+            },
+            LightKind::Rect => LightShape::Rect {
+                center: light.pos(),
+                edge_u: light.edge_u(),
+                edge_v: light.edge_v(),
+            },
+        };
+        Self {
+            shape,
+            strength: light.strength(),
+            color: light.color(),
+        }
+    }
+}
+
+impl From<&LightDesc> for Light {
+    fn from(desc: &LightDesc) -> Self {
+        let strength = desc.strength;
+        let mut light = match desc.shape {
+            LightShape::Point { pos } => Light::new(pos, strength),
+            LightShape::Directional { direction } => Light::directional(direction, strength),
+            LightShape::Spot {
+                pos,
+                direction,
+                inner_angle,
+                outer_angle,
+            } => Light::spot(pos, direction, inner_angle, outer_angle, strength),
+            LightShape::Sphere { center, radius } => Light::sphere(center, radius, strength),
+            LightShape::Rect {
+                center,
+                edge_u,
+                edge_v,
+            } => Light::rect(center, edge_u, edge_v, strength),
+        };
+        light.set_color(desc.color);
+        light
+    }
+}
+
+/// 材质, 参数和 [`Material`] 的各个构造函数一致.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MaterialDesc {
+    Diffuse {
+        albedo: Vec3,
+    },
+    Checker {
+        color_1: Vec3,
+        color_2: Vec3,
+        grid_size: f32,
+    },
+    Metal {
+        albedo: Vec3,
+    },
+    Dielectric {
+        ior: f32,
+        #[serde(default = "white")]
+        tint: Vec3,
+    },
+    Emissive {
+        emission: Vec3,
+    },
+}
+
+impl From<&Material> for MaterialDesc {
+    fn from(material: &Material) -> Self {
+        match material.kind() {
+            MaterialKind::Diffuse => match material.checker_pattern() {
+                Some((color_2, grid_size)) => Self::Checker {
+                    color_1: material.albedo(),
+                    color_2,
+                    grid_size,
+                },
+                None => Self::Diffuse {
+                    albedo: material.albedo(),
+                },
+            },
+            MaterialKind::Metal => Self::Metal {
+                albedo: material.albedo(),
+            },
+            MaterialKind::Dielectric => Self::Dielectric {
+                ior: material.ior(),
+                tint: material.albedo(),
+            },
+            MaterialKind::Emissive => Self::Emissive {
+                emission: material.emission(),
+            },
+        }
+    }
+}
+
+impl From<&MaterialDesc> for Material {
+    fn from(desc: &MaterialDesc) -> Self {
+        match *desc {
+            MaterialDesc::Diffuse { albedo } => Material::diffuse(albedo),
+            MaterialDesc::Checker {
+                color_1,
+                color_2,
+                grid_size,
+            } => Material::checker(color_1, color_2, grid_size),
+            MaterialDesc::Metal { albedo } => Material::metal(albedo),
+            MaterialDesc::Dielectric { ior, tint } => Material::tinted_dielectric(ior, tint),
+            MaterialDesc::Emissive { emission } => Material::emissive(emission),
+        }
+    }
+}
+
+/// 场景中的物体.
+///
+/// 用户自定义的 [`Hittable`](super::hittable::Hittable) 没有对应的描述, 保存场景时会被跳过.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ObjectDesc {
+    Sphere(SphereDesc),
+    Plane(PlaneDesc),
+    Mesh(MeshDesc),
+}
+
+impl ObjectDesc {
+    /// 场景中物体的描述, 只有球体, 平面和网格可以保存, 其他物体返回 None.
+    #[must_use]
+    pub fn from_object(object: &dyn Hittable) -> Option<Self> {
+        let any: &dyn Any = object;
+        if let Some(sphere) = any.downcast_ref::<Sphere>() {
+            Some(Self::Sphere(sphere.into()))
+        } else if let Some(plane) = any.downcast_ref::<Plane>() {
+            Some(Self::Plane(plane.into()))
+        } else {
+            any.downcast_ref::<Mesh>()
+                .map(|mesh| Self::Mesh(mesh.into()))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SphereDesc {
+    pub center: Vec3,
+    pub radius: f32,
+    pub material: MaterialDesc,
+}
+
+impl From<&Sphere> for SphereDesc {
+    fn from(sphere: &Sphere) -> Self {
+        Self {
+            center: sphere.center(),
+            radius: sphere.radius(),
+            material: MaterialDesc::from(&sphere.material()),
+        }
+    }
+}
+
+impl From<&SphereDesc> for Sphere {
+    fn from(desc: &SphereDesc) -> Self {
+        Sphere::with_material(desc.center, desc.radius, Material::from(&desc.material))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PlaneDesc {
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub material: MaterialDesc,
+}
+
+impl From<&Plane> for PlaneDesc {
+    fn from(plane: &Plane) -> Self {
+        Self {
+            point: plane.point(),
+            normal: plane.normal(),
+            material: MaterialDesc::from(&plane.material()),
+        }
+    }
+}
+
+impl From<&PlaneDesc> for Plane {
+    fn from(desc: &PlaneDesc) -> Self {
+        Plane::new(desc.point, desc.normal, Material::from(&desc.material))
+    }
+}
+
+/// 三角形网格, 顶点按逆时针顺序排列时几何法向量朝外.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeshDesc {
+    pub vertices: Vec<Vec3>,
+    /// 每个三角形三个顶点在 `vertices` 中的下标 (从 0 开始).
+    pub triangles: Vec<[usize; 3]>,
+    /// 顶点法向量, 和 `vertices` 一一对应, 用于平滑着色.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normals: Option<Vec<Vec3>>,
+    pub material: MaterialDesc,
+}
+
+impl From<&Mesh> for MeshDesc {
+    fn from(mesh: &Mesh) -> Self {
+        let smooth = mesh.triangles().iter().any(|t| t.normals().is_some());
+        let mut vertices = Vec::new();
+        let mut normals = Vec::new();
+        // 合并位置和法向量都相同的顶点.
+        let mut indices = HashMap::new();
+        let triangles = mesh
+            .triangles()
+            .iter()
+            .map(|triangle| {
+                let corner_normals = triangle
+                    .normals()
+                    .unwrap_or([triangle.normal_at(0., 0.); 3]);
+                let mut face = [0; 3];
+                for (i, (vertex, normal)) in triangle
+                    .vertices()
+                    .into_iter()
+                    .zip(corner_normals)
+                    .enumerate()
+                {
+                    let normal = if smooth { normal } else { Vec3::ZERO };
+                    let key = [vertex, normal].map(|v| [v.x, v.y, v.z].map(f32::to_bits));
+                    face[i] = *indices.entry(key).or_insert_with(|| {
+                        vertices.push(vertex);
+                        normals.push(normal);
+                        vertices.len() - 1
+                    });
+                }
+                face
+            })
+            .collect();
+        Self {
+            vertices,
+            triangles,
+            normals: smooth.then_some(normals),
+            material: MaterialDesc::from(&mesh.material()),
+        }
+    }
+}
+
+impl TryFrom<&MeshDesc> for Mesh {
+    type Error = String;
+
+    fn try_from(desc: &MeshDesc) -> Result<Self, Self::Error> {
+        if let Some(normals) = &desc.normals
+            && normals.len() != desc.vertices.len()
+        {
+            return Err(format!(
+                "mesh: {} normals for {} vertices",
+                normals.len(),
+                desc.vertices.len()
+            ));
+        }
+        if desc.triangles.is_empty() {
+            return Err("mesh: no triangles".to_string());
+        }
+        let triangles = desc
+            .triangles
+            .iter()
+            .map(|&face| {
+                if let Some(&i) = face.iter().find(|&&i| i >= desc.vertices.len()) {
+                    return Err(format!("mesh: vertex index {i} out of range"));
+                }
+                let vertices = face.map(|i| desc.vertices[i]);
+                Ok(match &desc.normals {
+                    Some(normals) => Triangle::with_normals(vertices, face.map(|i| normals[i])),
+                    None => Triangle::new(vertices),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Mesh::new(triangles, Material::from(&desc.material)))
+    }
+}
+
+/// 物理天空, 角度都是角度制.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SkyDesc {
+    pub sun_elevation: f32,
+    pub sun_azimuth: f32,
+    #[serde(default = "SkyDesc::default_turbidity")]
+    pub turbidity: f32,
+    #[serde(default = "SkyDesc::default_sun_strength")]
+    pub sun_strength: f32,
+}
+
+impl SkyDesc {
+    fn default_turbidity() -> f32 {
+        3.
+    }
+
+    fn default_sun_strength() -> f32 {
+        1.
+    }
+}
+
+impl From<&PhysicalSky> for SkyDesc {
+    fn from(sky: &PhysicalSky) -> Self {
+        Self {
+            sun_elevation: sky.sun_elevation(),
+            sun_azimuth: sky.sun_azimuth(),
+            turbidity: sky.turbidity(),
+            sun_strength: sky.sun_strength(),
+        }
+    }
+}
+
+impl From<&SkyDesc> for PhysicalSky {
+    fn from(desc: &SkyDesc) -> Self {
+        let mut sky = PhysicalSky::new(desc.sun_elevation, desc.sun_azimuth, desc.turbidity);
+        sky.set_sun_strength(desc.sun_strength);
+        sky
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scene_round_trips_through_json() {
+        let mut scene = Scene::from_json(include_str!("../../www/scenes/spheres.json")).unwrap();
+        scene.lights.push(LightDesc::from(&Light::rect(
+            Vec3::new(0., 0., 3.),
+            Vec3::X,
+            -Vec3::Y,
+            5.,
+        )));
+        scene.objects.push(ObjectDesc::Mesh(MeshDesc {
+            vertices: vec![Vec3::ZERO, Vec3::X, Vec3::Y],
+            triangles: vec![[0, 1, 2]],
+            normals: Some(vec![Vec3::Z; 3]),
+            material: MaterialDesc::from(&Material::diffuse(Vec3::new(0.5, 0.5, 0.5))),
+        }));
+        scene.sky = Some(SkyDesc::from(&PhysicalSky::new(0.5, 1., 3.)));
+
+        let json = scene.to_json().unwrap();
+        let round_trip = Scene::from_json(&json).unwrap();
+        assert_eq!(round_trip.objects.len(), 7);
+        assert_eq!(round_trip.lights.len(), 3);
+        assert_eq!(round_trip.to_json().unwrap(), json);
+    }
+
+    #[test]
+    fn renderer_keeps_scene_objects() {
+        let scene = Scene::from_json(include_str!("../../www/scenes/spheres.json")).unwrap();
+        let ray_tracing = RayTracing::from_scene(&scene, 4, 4, 0).unwrap();
+        let saved = ray_tracing.to_scene();
+        let json = |objects: &[ObjectDesc]| serde_json::to_string(objects).unwrap();
+        assert_eq!(json(&saved.objects), json(&scene.objects));
+    }
+}
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::ray_tracing::tone_mapping::ToneMapping;
+use crate::ray_tracing::vector::Vec3;
+
+/// 积分器, 决定怎么计算一条光线带回来的颜色.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Integrator {
+    /// Whitted 风格: 点光源直接光照 + 完美镜面递归, 速度快.
+    Whitted,
+    /// 蒙特卡洛路径追踪, 有间接光照 (颜色溢出), 需要渐进式渲染累积才能收敛.
+    PathTracing,
+}
+
+/// 渲染参数, 可以在运行时通过 [`RayTracing::set_settings`](super::RayTracing::set_settings) 修改,
+/// 用画质换取帧率.
+///
+/// 地面颜色和球体反射率属于材质, 不在这里设置: 地面通过
+/// [`RayTracing::set_ground_material`](super::RayTracing::set_ground_material) 或者场景文件的 `ground` 修改,
+/// 球体的默认材质是 [`Material::default`](super::material::Material::default).
+///
+/// 在场景文件中缺少的字段使用默认值.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderSettings {
+    /// 每个像素的抗锯齿采样次数.
+    pub aa_samples: u16,
+    /// 最大的反射 (折射) 次数.
+    pub max_reflection: u32,
+    /// 薄透镜相机的光圈半径 (米), 0 表示针孔相机, 没有景深. 只对透视投影生效.
+    pub aperture: f32,
+    /// 对焦距离 (米), 沿视线方向测量, 这个距离上的物体最清晰.
+    pub focus_distance: f32,
+    /// 自动对焦: 对焦到画面中心看到的物体上, 没有打中物体时使用 `focus_distance`.
+    pub autofocus: bool,
+    /// 高光幂次.
+    pub specular_pow: f32,
+    /// 天空颜色, 设置了环境贴图时不使用.
+    pub sky_color: Vec3,
+    /// 使用的积分器.
+    pub integrator: Integrator,
+    /// 路径追踪的最大弹射次数, 在此之前由俄罗斯轮盘赌随机终止.
+    pub max_bounces: u32,
+    /// 画面静止时是否继续渲染并累积结果, 让画面逐渐收敛.
+    pub progressive: bool,
+    /// 渐进式渲染最多累积的次数, 达到之后不再渲染.
+    pub max_progressive_passes: u32,
+    /// 曝光补偿 (EV), 每增加 1 画面亮度翻倍.
+    pub exposure: f32,
+    /// 色调映射算子, 之后还会进行 sRGB 编码.
+    pub tone_mapping: ToneMapping,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            aa_samples: 5,
+            max_reflection: 3,
+            aperture: 0.,
+            focus_distance: 5.,
+            autofocus: true,
+            specular_pow: 80.,
+            sky_color: Vec3::new(0.7, 0.6, 1.0),
+            integrator: Integrator::Whitted,
+            max_bounces: 16,
+            progressive: true,
+            max_progressive_passes: 100,
+            exposure: 0.,
+            tone_mapping: ToneMapping::Aces,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl RenderSettings {
+    /// 默认的渲染参数.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
@@ -0,0 +1,219 @@
+use std::f32;
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::ray_tracing::Light;
+use crate::ray_tracing::vector::Vec3;
+
+/// Preetham 解析天空模型, 由太阳位置和大气浑浊度决定天空颜色,
+/// 同时带有一个方向和颜色一致的太阳平行光.
+///
+/// 太阳在地平线以下时天空和太阳光逐渐变暗, 可以用来模拟一天中的时间变化.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct PhysicalSky {
+    /// 太阳高度角 (角度制), 地平线为 0.
+    sun_elevation: f32,
+    /// 太阳方位角 (角度制), 从 +x 轴开始逆时针 (俯视) 旋转.
+    sun_azimuth: f32,
+    /// 大气浑浊度, 2 是非常晴朗, 10 是雾霾.
+    turbidity: f32,
+    /// 太阳正对时的光照强度.
+    sun_strength: f32,
+
+    // 以下由上面的参数计算得到.
+    /// 指向太阳的标准化方向.
+    sun_direction: Vec3,
+    /// 天顶的 (亮度 Y, 色度 x, 色度 y).
+    zenith: [f32; 3],
+    /// Y, x, y 三个分量的 Perez 系数 A ~ E.
+    perez: [[f32; 5]; 3],
+    /// 太阳在地平线附近时的整体亮度.
+    fade: f32,
+    pub(crate) sun: Light,
+}
+
+#[wasm_bindgen]
+impl PhysicalSky {
+    /// 高度角和方位角都是角度制.
+    #[must_use]
+    pub fn new(sun_elevation: f32, sun_azimuth: f32, turbidity: f32) -> Self {
+        let mut sky = Self {
+            sun_elevation,
+            sun_azimuth,
+            turbidity: turbidity.clamp(1.7, 10.),
+            sun_strength: 1.,
+            sun_direction: Vec3::Z,
+            zenith: [0.; 3],
+            perez: [[0.; 5]; 3],
+            fade: 1.,
+            sun: Light::directional(-Vec3::Z, 0.),
+        };
+        sky.update();
+        sky
+    }
+
+    #[must_use]
+    pub fn sun_elevation(&self) -> f32 {
+        self.sun_elevation
+    }
+
+    #[must_use]
+    pub fn sun_azimuth(&self) -> f32 {
+        self.sun_azimuth
+    }
+
+    /// 设置太阳位置, 高度角和方位角都是角度制.
+    pub fn set_sun_position(&mut self, elevation: f32, azimuth: f32) {
+        self.sun_elevation = elevation;
+        self.sun_azimuth = azimuth;
+        self.update();
+    }
+
+    /// 按一天中的时间 (小时, 0 ~ 24) 设置太阳位置:
+    /// 6 点从 +x 方向升起, 12 点在 +y 方向最高, 18 点从 -x 方向落下.
+    pub fn set_time_of_day(&mut self, hours: f32) {
+        let angle = (hours - 6.) / 12. * f32::consts::PI;
+        self.set_sun_position(Self::MAX_SUN_ELEVATION * angle.sin(), (hours - 6.) * 15.);
+    }
+
+    #[must_use]
+    pub fn turbidity(&self) -> f32 {
+        self.turbidity
+    }
+
+    /// 设置大气浑浊度, 限制在 1.7 ~ 10.
+    pub fn set_turbidity(&mut self, turbidity: f32) {
+        self.turbidity = turbidity.clamp(1.7, 10.);
+        self.update();
+    }
+
+    #[must_use]
+    pub fn sun_strength(&self) -> f32 {
+        self.sun_strength
+    }
+
+    pub fn set_sun_strength(&mut self, strength: f32) {
+        self.sun_strength = strength;
+        self.update();
+    }
+
+    /// 和天空匹配的太阳平行光.
+    #[must_use]
+    pub fn sun(&self) -> Light {
+        self.sun.clone()
+    }
+}
+
+impl PhysicalSky {
+    /// 天空亮度的缩放, 把 Preetham 模型的亮度 (kcd/m²) 换算到渲染使用的范围.
+    const LUMINANCE_SCALE: f32 = 0.04;
+    /// 一天中太阳的最大高度角 (角度制).
+    const MAX_SUN_ELEVATION: f32 = 65.;
+
+    /// 参数修改之后重新计算天顶颜色, Perez 系数和太阳光.
+    fn update(&mut self) {
+        let (sin_azimuth, cos_azimuth) = self.sun_azimuth.to_radians().sin_cos();
+        let (sin_elevation, cos_elevation) = self.sun_elevation.to_radians().sin_cos();
+        self.sun_direction = Vec3::new(
+            cos_elevation * cos_azimuth,
+            cos_elevation * sin_azimuth,
+            sin_elevation,
+        );
+
+        // Preetham 模型只适用于地平线以上的太阳, 太阳落下之后沿用地平线处的颜色并逐渐变暗.
+        let theta_s = (90. - self.sun_elevation.max(0.)).to_radians();
+        let t = self.turbidity;
+        let chi = (4.0 / 9.0 - t / 120.0) * (f32::consts::PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let chromaticity = |m: [[f32; 4]; 3]| {
+            let theta = [theta_s.powi(3), theta_s.powi(2), theta_s, 1.0];
+            let row = |r: [f32; 4]| r.iter().zip(theta).map(|(a, b)| a * b).sum::<f32>();
+            t * t * row(m[0]) + t * row(m[1]) + row(m[2])
+        };
+        let zenith_x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+        self.zenith = [zenith_luminance.max(0.), zenith_x, zenith_y];
+        self.perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+        // 太阳从地平线以上 2 度到以下 6 度 (民用晨昏蒙影) 之间平滑变暗.
+        let fade = ((self.sun_elevation + 6.) / 8.).clamp(0., 1.);
+        self.fade = fade * fade * (3. - 2. * fade);
+
+        // 太阳光穿过大气的透射率: 高度角越低, 经过的大气越厚, 蓝光被散射得越多.
+        let zenith_angle = 90. - self.sun_elevation.max(0.);
+        let air_mass =
+            1.0 / (zenith_angle.to_radians().cos() + 0.15 * (93.885 - zenith_angle).powf(-1.253));
+        let extinction = Vec3::new(0.06, 0.12, 0.28) * (t / 2.0);
+        let transmittance = Vec3::new(
+            (-extinction.x * air_mass).exp(),
+            (-extinction.y * air_mass).exp(),
+            (-extinction.z * air_mass).exp(),
+        );
+        let mut sun = Light::directional(-self.sun_direction, self.sun_strength * self.fade);
+        sun.set_color(transmittance);
+        self.sun = sun;
+    }
+
+    /// Perez 天空亮度分布函数, `theta` 是和天顶的夹角, `gamma` 是和太阳的夹角.
+    fn perez([a, b, c, d, e]: [f32; 5], theta: f32, gamma: f32) -> f32 {
+        (1.0 + a * (b / theta.cos()).exp())
+            * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+    }
+
+    /// 沿 `direction` (标准化) 看到的天空颜色 (线性 rgb).
+    #[must_use]
+    pub fn radiance(&self, direction: Vec3) -> Vec3 {
+        // 地平线以下使用地平线处的颜色.
+        let direction = Vec3::new(direction.x, direction.y, direction.z.max(0.001)).normalize();
+        let theta = direction.z.acos();
+        let gamma = direction.dot(self.sun_direction).clamp(-1.0, 1.0).acos();
+        let theta_s = (90. - self.sun_elevation.max(0.)).to_radians();
+        let [luminance, x, y] = [0, 1, 2].map(|i| {
+            self.zenith[i] * Self::perez(self.perez[i], theta, gamma)
+                / Self::perez(self.perez[i], 0.0, theta_s)
+        });
+        let luminance = luminance * Self::LUMINANCE_SCALE * self.fade;
+        if y <= 0.0 {
+            return Vec3::ZERO;
+        }
+
+        // xyY -> XYZ -> 线性 sRGB.
+        let big_x = x / y * luminance;
+        let big_z = (1.0 - x - y) / y * luminance;
+        Vec3::new(
+            (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.),
+            (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.),
+            (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.),
+        )
+    }
+}
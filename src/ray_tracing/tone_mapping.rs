@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::ray_tracing::rgbf;
+use crate::ray_tracing::settings::RenderSettings;
+use crate::ray_tracing::vector::Vec3;
+
+/// 色调映射算子, 把没有上限的线性颜色压缩到 0.0 ~ 1.0.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToneMapping {
+    /// 直接截断到 0.0 ~ 1.0, 高光会过曝成纯色.
+    Clamp,
+    /// Reinhard: `x / (1 + x)`, 高光平滑过渡, 整体偏灰.
+    Reinhard,
+    /// ACES 电影曲线 (Narkowicz 拟合), 对比度更高, 高光偏白.
+    Aces,
+}
+
+impl ToneMapping {
+    /// 对线性颜色的每个分量进行色调映射.
+    #[must_use]
+    pub fn apply(self, color: Vec3) -> Vec3 {
+        let map = |x: f32| {
+            let x = x.max(0.0);
+            match self {
+                ToneMapping::Clamp => x.min(1.0),
+                ToneMapping::Reinhard => x / (1.0 + x),
+                ToneMapping::Aces => {
+                    ((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0.0, 1.0)
+                }
+            }
+        };
+        Vec3::new(map(color.x), map(color.y), map(color.z))
+    }
+}
+
+/// 线性值 (0.0 ~ 1.0) 转 sRGB 编码.
+#[must_use]
+pub fn linear_to_srgb(x: f32) -> f32 {
+    if x <= 0.003_130_8 {
+        x * 12.92
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// 后期处理: 曝光, 色调映射, sRGB 编码, 最后打包成 0xAARRGGBB.
+#[must_use]
+pub fn post_process(color: Vec3, settings: &RenderSettings) -> u32 {
+    let color = settings
+        .tone_mapping
+        .apply(color * settings.exposure.exp2());
+    rgbf(
+        linear_to_srgb(color.x),
+        linear_to_srgb(color.y),
+        linear_to_srgb(color.z),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [ToneMapping; 3] = [ToneMapping::Clamp, ToneMapping::Reinhard, ToneMapping::Aces];
+
+    fn map(tone_mapping: ToneMapping, x: f32) -> f32 {
+        tone_mapping.apply(Vec3::new(x, x, x)).x
+    }
+
+    #[test]
+    fn curves_map_black_to_black_and_stay_below_white() {
+        for tone_mapping in ALL {
+            assert_eq!(map(tone_mapping, 0.), 0.);
+            assert_eq!(map(tone_mapping, -1.), 0.);
+            assert!(map(tone_mapping, 1e6) <= 1.);
+            // 单调不减.
+            let samples = (0..100).map(|i| map(tone_mapping, i as f32 * 0.1));
+            let values = samples.collect::<Vec<_>>();
+            assert!(values.windows(2).all(|w| w[0] <= w[1]), "{tone_mapping:?}");
+        }
+        assert_eq!(map(ToneMapping::Clamp, 1.), 1.);
+        assert_eq!(map(ToneMapping::Reinhard, 1.), 0.5);
+        assert!(map(ToneMapping::Reinhard, 1e6) > 0.99);
+        assert_eq!(map(ToneMapping::Aces, 1e6), 1.);
+    }
+
+    #[test]
+    fn srgb_endpoints() {
+        assert_eq!(linear_to_srgb(0.), 0.);
+        assert!((linear_to_srgb(1.) - 1.).abs() < 1e-6);
+        // 线性段和幂函数段在分界点连续.
+        let below = linear_to_srgb(0.003_130_8);
+        let above = linear_to_srgb(0.003_130_9);
+        assert!((above - below).abs() < 1e-4);
+    }
+
+    #[test]
+    fn post_process_endpoints() {
+        let settings = RenderSettings {
+            tone_mapping: ToneMapping::Clamp,
+            ..RenderSettings::default()
+        };
+        assert_eq!(post_process(Vec3::ZERO, &settings), 0xff00_0000);
+        assert_eq!(post_process(Vec3::new(1., 1., 1.), &settings), 0xffff_ffff);
+        // 曝光 +1 档相当于亮度乘 2.
+        let brighter = RenderSettings {
+            exposure: 1.,
+            ..settings
+        };
+        assert_eq!(
+            post_process(Vec3::new(0.25, 0.25, 0.25), &brighter),
+            post_process(Vec3::new(0.5, 0.5, 0.5), &settings)
+        );
+    }
+}
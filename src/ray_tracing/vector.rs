@@ -1,12 +1,16 @@
 use std::ops::{Add, Div, Mul, Neg, Sub};
 
+use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::wasm_bindgen;
 #[cfg(feature = "simd")]
 use wide::{f32x4, f32x8};
 
 /// 右手坐标系, z 轴向上.
+///
+/// 在场景文件中序列化为 `[x, y, z]`.
 #[wasm_bindgen]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(from = "[f32; 3]", into = "[f32; 3]")]
 pub struct Vec3 {
     pub x: f32,
     pub y: f32,
@@ -34,6 +38,27 @@ impl Mul<f32> for Vec3 {
     }
 }
 
+/// 向量逐元素相乘 (用于颜色混合)
+impl Mul for Vec3 {
+    type Output = Vec3;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        #[cfg(feature = "simd")]
+        {
+            let self_simd = f32x4::new([self.x, self.y, self.z, 0.]);
+            let rhs_simd = f32x4::new([rhs.x, rhs.y, rhs.z, 0.]);
+            let [x, y, z, _] = (self_simd * rhs_simd).to_array();
+            Self { x, y, z }
+        }
+        #[cfg(not(feature = "simd"))]
+        Self {
+            x: self.x * rhs.x,
+            y: self.y * rhs.y,
+            z: self.z * rhs.z,
+        }
+    }
+}
+
 /// 向量除以标量
 impl Div<f32> for Vec3 {
     type Output = Vec3;
@@ -122,6 +147,18 @@ impl From<(f32, f32, f32)> for Vec3 {
     }
 }
 
+impl From<[f32; 3]> for Vec3 {
+    fn from([x, y, z]: [f32; 3]) -> Self {
+        Vec3 { x, y, z }
+    }
+}
+
+impl From<Vec3> for [f32; 3] {
+    fn from(value: Vec3) -> Self {
+        [value.x, value.y, value.z]
+    }
+}
+
 impl Vec3 {
     const TOLERANCE: f32 = 1e-6;
     pub const ZERO: Vec3 = Vec3::new_const(0., 0., 0.);
@@ -215,6 +252,12 @@ impl Vec3 {
         }
     }
 
+    /// 以标准化的 `normal` 为法向量计算反射方向.
+    #[must_use]
+    pub fn reflect(self, normal: Self) -> Self {
+        self - normal * (2.0 * self.dot(normal))
+    }
+
     /// 计算两个向量之间的余弦相似度.
     #[must_use]
     pub fn cos(self, rhs: Self) -> f32 {
@@ -223,3 +266,10 @@ impl Vec3 {
         self.dot(rhs) / self_mag / rhs_mag
     }
 }
+
+/// 测试用的近似比较. 开启 simd 时 [`Vec3::normalize`] 使用近似的平方根倒数,
+/// 相对误差在 1e-4 左右, 所以容差比 `Vec3::TOLERANCE` 宽.
+#[cfg(test)]
+pub(crate) fn approx_eq(a: Vec3, b: Vec3) -> bool {
+    (a - b).magnitude() < 1e-3
+}